@@ -0,0 +1,320 @@
+//! Python bindings over the library's `Converter`: `import rust_ocr2md`.
+
+use ocr2md::config;
+use ocr2md::ocr_engine::{BlockType, OcrBlock};
+use ocr2md::{
+    ConvertOptions, Converter, Document, Event, PageFailure, PageResult, ProgressHandler,
+};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::{Path, PathBuf};
+
+create_exception!(rust_ocr2md, ConversionError, PyException);
+
+fn conversion_error(e: anyhow::Error) -> PyErr {
+    ConversionError::new_err(format!("{:#}", e))
+}
+
+/// Round-trip through JSON, which both sides already speak.
+fn to_python(py: Python<'_>, json: &str) -> PyResult<PyObject> {
+    Ok(py
+        .import_bound("json")?
+        .call_method1("loads", (json,))?
+        .unbind())
+}
+
+fn from_python(value: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    let json: String = value
+        .py()
+        .import_bound("json")?
+        .call_method1("dumps", (value,))?
+        .extract()?;
+    serde_json::from_str(&json).map_err(|e| conversion_error(e.into()))
+}
+
+/// Calls a Python callable with every progress event as a dict.
+struct PyProgress(PyObject);
+
+impl ProgressHandler for PyProgress {
+    fn on_event(&self, event: &Event) {
+        Python::with_gil(|py| {
+            let result = serde_json::to_string(event)
+                .map_err(|e| conversion_error(e.into()))
+                .and_then(|json| to_python(py, &json))
+                .and_then(|event| self.0.call1(py, (event,)));
+            // An exception in a callback must not abort the conversion
+            if let Err(e) = result {
+                e.print(py);
+            }
+        });
+    }
+}
+
+/// Converts PDFs and images to Markdown. OCR models are loaded on first use and reused.
+///
+/// Configuration comes from the same files and profiles as the command line, with
+/// keyword arguments on top, e.g. `Converter(profile="paper", dpi=350, cache={"enabled": False})`.
+#[pyclass(name = "Converter", frozen)]
+struct PyConverter {
+    inner: Converter,
+}
+
+#[pymethods]
+impl PyConverter {
+    #[new]
+    #[pyo3(signature = (config=None, profile=None, password=None, progress=None, **overrides))]
+    fn new(
+        config: Option<PathBuf>,
+        profile: Option<&str>,
+        password: Option<String>,
+        progress: Option<PyObject>,
+        overrides: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Self> {
+        let mut loaded = config::load(config.as_deref(), profile).map_err(conversion_error)?;
+        if let Some(overrides) = overrides {
+            let table: toml::Table = serde_json::from_value(from_python(overrides.as_any())?)
+                .map_err(|e| conversion_error(e.into()))?;
+            loaded.apply_overrides(table).map_err(conversion_error)?;
+        }
+
+        let mut inner = Converter::new(loaded.config)
+            .with_explicit_keys(loaded.explicit)
+            .with_password(password);
+        if let Some(callback) = progress {
+            inner = inner.with_progress(PyProgress(callback));
+        }
+        Ok(Self { inner })
+    }
+
+    /// The effective configuration as a dict.
+    #[getter]
+    fn config(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let json =
+            serde_json::to_string(self.inner.config()).map_err(|e| conversion_error(e.into()))?;
+        to_python(py, &json)
+    }
+
+    /// Load the OCR models now instead of on the first conversion.
+    fn load_engine(&self, py: Python<'_>) -> PyResult<()> {
+        py.allow_threads(|| self.inner.load_engine())
+            .map_err(conversion_error)
+    }
+
+    /// Convert a PDF, image, multi-page TIFF or directory of images.
+    #[pyo3(signature = (path, pages=None, assets_dir=None))]
+    fn convert_file(
+        &self,
+        py: Python<'_>,
+        path: PathBuf,
+        pages: Option<String>,
+        assets_dir: Option<PathBuf>,
+    ) -> PyResult<PyDocument> {
+        let options = ConvertOptions {
+            pages,
+            assets_dir,
+            ..Default::default()
+        };
+        let document = py
+            .allow_threads(|| self.inner.convert(&path, &options))
+            .map_err(conversion_error)?;
+        Ok(PyDocument::from(document))
+    }
+
+    /// Convert a PDF held in memory; `name` is used in the Markdown header and cache keys.
+    #[pyo3(signature = (data, pages=None, assets_dir=None, name="document.pdf"))]
+    fn convert_bytes(
+        &self,
+        py: Python<'_>,
+        data: &[u8],
+        pages: Option<String>,
+        assets_dir: Option<PathBuf>,
+        name: &str,
+    ) -> PyResult<PyDocument> {
+        let options = ConvertOptions {
+            pages,
+            assets_dir,
+            ..Default::default()
+        };
+        let data = data.to_vec();
+        let name = Path::new(name);
+        let document = py
+            .allow_threads(|| self.inner.convert_bytes_with(data, name, &options))
+            .map_err(conversion_error)?;
+        Ok(PyDocument::from(document))
+    }
+}
+
+/// One recognized block; `bbox` is `(x, y, width, height)` in page image pixels.
+#[pyclass(name = "Block", get_all, frozen)]
+#[derive(Clone)]
+struct PyBlock {
+    text: String,
+    /// "text", "formula", "table", "figure" or "unknown"
+    block_type: &'static str,
+    confidence: f32,
+    bbox: Option<(u32, u32, u32, u32)>,
+    language: Option<String>,
+    /// Asset file name of a figure
+    image: Option<String>,
+}
+
+#[pymethods]
+impl PyBlock {
+    fn __repr__(&self) -> String {
+        format!(
+            "Block(block_type={:?}, confidence={:.2}, text={:?})",
+            self.block_type, self.confidence, self.text
+        )
+    }
+}
+
+impl From<&OcrBlock> for PyBlock {
+    fn from(block: &OcrBlock) -> Self {
+        Self {
+            text: block.text.clone(),
+            block_type: match block.block_type {
+                BlockType::Text => "text",
+                BlockType::Formula => "formula",
+                BlockType::Table => "table",
+                BlockType::Figure => "figure",
+                BlockType::Unknown => "unknown",
+            },
+            confidence: block.confidence,
+            bbox: block.bbox.as_ref().map(|b| (b.x, b.y, b.width, b.height)),
+            language: block.language.clone(),
+            image: block.image.clone(),
+        }
+    }
+}
+
+#[pyclass(name = "Page", get_all, frozen)]
+#[derive(Clone)]
+struct PyPage {
+    /// 1-based page number
+    number: usize,
+    width: u32,
+    height: u32,
+    detected_language: Option<String>,
+    blocks: Vec<PyBlock>,
+}
+
+#[pymethods]
+impl PyPage {
+    fn __repr__(&self) -> String {
+        format!("Page(number={}, blocks={})", self.number, self.blocks.len())
+    }
+}
+
+impl From<&PageResult> for PyPage {
+    fn from(page: &PageResult) -> Self {
+        Self {
+            number: page.page_num,
+            width: page.image_width,
+            height: page.image_height,
+            detected_language: page.detected_language.clone(),
+            blocks: page.blocks.iter().map(PyBlock::from).collect(),
+        }
+    }
+}
+
+#[pyclass(name = "PageFailure", get_all, frozen)]
+#[derive(Clone)]
+struct PyPageFailure {
+    page: usize,
+    /// Pipeline stage, e.g. "render" or "ocr"
+    stage: String,
+    message: String,
+}
+
+#[pymethods]
+impl PyPageFailure {
+    fn __repr__(&self) -> String {
+        format!(
+            "PageFailure(page={}, stage={:?}, message={:?})",
+            self.page, self.stage, self.message
+        )
+    }
+}
+
+impl From<&PageFailure> for PyPageFailure {
+    fn from(failure: &PageFailure) -> Self {
+        Self {
+            page: failure.page,
+            stage: failure.stage.to_string(),
+            message: failure.message.clone(),
+        }
+    }
+}
+
+/// A converted document.
+#[pyclass(name = "Document", frozen)]
+struct PyDocument {
+    #[pyo3(get)]
+    input: String,
+    #[pyo3(get)]
+    markdown: String,
+    /// Pages that converted, in page order
+    #[pyo3(get)]
+    pages: Vec<PyPage>,
+    #[pyo3(get)]
+    failures: Vec<PyPageFailure>,
+    /// Number of pages selected for conversion
+    #[pyo3(get)]
+    selected: usize,
+    /// `DocumentReport` as JSON
+    report: String,
+}
+
+impl From<Document> for PyDocument {
+    fn from(document: Document) -> Self {
+        Self {
+            input: document.input.display().to_string(),
+            markdown: document.markdown,
+            pages: document.pages.iter().map(PyPage::from).collect(),
+            failures: document.failures.iter().map(PyPageFailure::from).collect(),
+            selected: document.selected,
+            report: serde_json::to_string(&document.report).unwrap_or_default(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyDocument {
+    /// Per-page timings, confidence and warnings as a dict, as written by `--report`.
+    #[getter]
+    fn report(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_python(py, &self.report)
+    }
+
+    /// Every selected page converted.
+    fn is_complete(&self) -> bool {
+        self.pages.len() == self.selected
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Document(input={:?}, pages={}/{}, failures={})",
+            self.input,
+            self.pages.len(),
+            self.selected,
+            self.failures.len()
+        )
+    }
+}
+
+#[pymodule]
+fn rust_ocr2md(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add(
+        "ConversionError",
+        m.py().get_type_bound::<ConversionError>(),
+    )?;
+    m.add_class::<PyConverter>()?;
+    m.add_class::<PyDocument>()?;
+    m.add_class::<PyPage>()?;
+    m.add_class::<PyBlock>()?;
+    m.add_class::<PyPageFailure>()?;
+    Ok(())
+}
@@ -0,0 +1,685 @@
+use crate::config::{Config, EngineKind};
+use crate::language::LanguageDetector;
+use crate::math;
+use crate::page_source::PageSource;
+use anyhow::Result;
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Pages rendered and inspected per document.
+const SAMPLE_PAGES: usize = 5;
+/// Render resolution for samples; enough for layout and quality statistics.
+const SAMPLE_DPI: u32 = 100;
+/// Pages with at least this many non-space text-layer characters count as born-digital.
+const MIN_TEXT_LAYER_CHARS: usize = 200;
+/// Luma value below which a pixel counts as ink.
+const INK_LUMA: u8 = 160;
+/// A gap between columns must be at least this fraction of the page width.
+const MIN_GUTTER_RATIO: f32 = 0.02;
+/// Pixel columns with at most this fraction of ink rows count as empty.
+const GUTTER_INK_RATIO: f32 = 0.01;
+/// Share of formula-like lines above which math OCR is worth its cost.
+const FORMULA_LINE_RATIO: f32 = 0.02;
+/// A script needs at least this share of letters to count.
+const MIN_SCRIPT_SHARE: f32 = 0.05;
+/// Width samples are scaled to before measuring quality, so scores compare across inputs.
+const QUALITY_WIDTH: u32 = 1000;
+/// Luma standard deviation below which a scan counts as low contrast.
+const MIN_CONTRAST: f32 = 40.0;
+/// Mean absolute Laplacian below which a scan counts as blurry.
+const MIN_SHARPNESS: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    BornDigital,
+    Scanned,
+    /// Some sampled pages have a text layer, some do not
+    Mixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Paper,
+    Book,
+    Slides,
+    Document,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageQuality {
+    /// Standard deviation of luma
+    pub contrast: f32,
+    /// Mean absolute Laplacian at a fixed width
+    pub sharpness: f32,
+}
+
+impl ImageQuality {
+    pub fn is_poor(&self) -> bool {
+        self.contrast < MIN_CONTRAST || self.sharpness < MIN_SHARPNESS
+    }
+}
+
+/// What a sample of pages says about a document.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// 1-based pages that were inspected
+    pub sampled: Vec<usize>,
+    pub origin: Origin,
+    pub kind: DocumentKind,
+    /// Median text column count of the sampled pages
+    pub columns: usize,
+    /// Tesseract language codes found in the text layer, most frequent first
+    pub languages: Vec<&'static str>,
+    /// Share of text-layer lines that look like formulas; `None` without a text layer
+    pub formula_density: Option<f32>,
+    /// Average quality of pages without a text layer
+    pub quality: Option<ImageQuality>,
+}
+
+/// One setting auto-config wanted to change.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub key: &'static str,
+    pub from: String,
+    pub to: String,
+    pub reason: &'static str,
+    /// False when the user set the key explicitly and it was left alone
+    pub applied: bool,
+}
+
+/// Render a few pages at low resolution and classify the document.
+pub fn analyze(source: &dyn PageSource, config: &Config) -> Result<Analysis> {
+    let candidates = sample_pages(source.page_count());
+
+    let mut sample_config = config.clone();
+    sample_config.dpi = SAMPLE_DPI;
+    sample_config.figures = false;
+    sample_config.annotations = false;
+
+    let mut sampled = Vec::new();
+    let mut digital_pages = 0;
+    let mut landscape_pages = 0;
+    let mut columns = Vec::new();
+    let mut qualities = Vec::new();
+    let mut text = String::new();
+
+    for page_num in candidates {
+        // A page that fails to render here fails again later and is reported there
+        let Ok(rendered) = source.render_page(page_num, &sample_config) else {
+            continue;
+        };
+        sampled.push(page_num);
+
+        let layer = source.text_layer(page_num).unwrap_or_default();
+        let layer_chars = layer.chars().filter(|c| !c.is_whitespace()).count();
+        if layer_chars >= MIN_TEXT_LAYER_CHARS {
+            digital_pages += 1;
+            text.push_str(&layer);
+            text.push('\n');
+        } else {
+            qualities.push(measure_quality(&rendered.image));
+        }
+
+        let gray = rendered.image.to_luma8();
+        if gray.width() as f32 > gray.height() as f32 * 1.15 {
+            landscape_pages += 1;
+        }
+        columns.push(count_columns(&gray));
+    }
+
+    if sampled.is_empty() {
+        anyhow::bail!("none of the sampled pages could be rendered");
+    }
+
+    let origin = match digital_pages {
+        0 => Origin::Scanned,
+        n if n == sampled.len() => Origin::BornDigital,
+        _ => Origin::Mixed,
+    };
+
+    columns.sort_unstable();
+    let columns = columns[columns.len() / 2];
+
+    let formula_density = (!text.trim().is_empty()).then(|| measure_formula_density(&text));
+    let quality = (!qualities.is_empty()).then(|| ImageQuality {
+        contrast: qualities.iter().map(|q| q.contrast).sum::<f32>() / qualities.len() as f32,
+        sharpness: qualities.iter().map(|q| q.sharpness).sum::<f32>() / qualities.len() as f32,
+    });
+
+    let page_count = source.page_count();
+    let kind = if landscape_pages * 2 > sampled.len() {
+        DocumentKind::Slides
+    } else if columns == 1
+        && (page_count >= 120 || (page_count >= 60 && source.outline().len() >= 10))
+    {
+        DocumentKind::Book
+    } else if page_count <= 60
+        && (columns >= 2 || formula_density.is_some_and(|d| d >= FORMULA_LINE_RATIO))
+    {
+        DocumentKind::Paper
+    } else {
+        DocumentKind::Document
+    };
+
+    Ok(Analysis {
+        sampled,
+        origin,
+        kind,
+        columns,
+        languages: text_languages(&text),
+        formula_density,
+        quality,
+    })
+}
+
+impl Analysis {
+    /// Adjust `config` to the document, leaving keys in `explicit` untouched.
+    pub fn tune(&self, config: &mut Config, explicit: &BTreeSet<String>) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let scanned = self.origin != Origin::BornDigital;
+        let poor_scan = self.quality.is_some_and(|q| q.is_poor());
+        let has_cjk = self
+            .languages
+            .iter()
+            .any(|l| matches!(*l, "chi_sim" | "jpn" | "kor"));
+
+        let mut propose =
+            |key: &'static str, target: &mut dyn Setting, value: String, reason: &'static str| {
+                let from = target.show();
+                if from == value {
+                    return;
+                }
+                let applied = !explicit.contains(key);
+                if applied {
+                    target.assign(&value);
+                }
+                changes.push(Change {
+                    key,
+                    from,
+                    to: value,
+                    reason,
+                    applied,
+                });
+            };
+
+        if poor_scan {
+            propose("dpi", &mut config.dpi, "400".into(), "low-quality scan");
+        } else if self.kind == DocumentKind::Slides {
+            propose("dpi", &mut config.dpi, "200".into(), "large slide text");
+        }
+
+        if scanned {
+            propose(
+                "preprocess",
+                &mut config.preprocess,
+                "true".into(),
+                "scanned pages",
+            );
+        } else {
+            propose(
+                "preprocess",
+                &mut config.preprocess,
+                "false".into(),
+                "clean born-digital pages",
+            );
+        }
+
+        if has_cjk {
+            propose("engine", &mut config.engine, "paddle".into(), "CJK text");
+        } else if poor_scan {
+            propose("engine", &mut config.engine, "paddle".into(), "noisy scan");
+        }
+
+        if self.columns > 1 {
+            propose(
+                "layout",
+                &mut config.layout,
+                "true".into(),
+                "multi-column pages",
+            );
+        } else if self.kind == DocumentKind::Slides {
+            propose("layout", &mut config.layout, "true".into(), "slide layout");
+        }
+
+        match self.formula_density {
+            Some(density) if density >= FORMULA_LINE_RATIO => {
+                propose(
+                    "math_ocr",
+                    &mut config.math_ocr,
+                    "true".into(),
+                    "formula-dense text",
+                );
+            }
+            Some(density) if density < FORMULA_LINE_RATIO / 4.0 => {
+                propose(
+                    "math_ocr",
+                    &mut config.math_ocr,
+                    "false".into(),
+                    "no formulas in text layer",
+                );
+            }
+            None if self.kind == DocumentKind::Paper => {
+                propose(
+                    "math_ocr",
+                    &mut config.math_ocr,
+                    "true".into(),
+                    "paper layout",
+                );
+            }
+            _ => {}
+        }
+
+        if !self.languages.is_empty() {
+            let mut languages = self.languages.join("+");
+            if config.math_ocr && config.languages.split('+').any(|l| l == "equ") {
+                languages.push_str("+equ");
+            }
+            propose(
+                "languages",
+                &mut config.languages,
+                languages,
+                "scripts in text layer",
+            );
+            propose(
+                "detect_language",
+                &mut config.detect_language,
+                "false".into(),
+                "languages known from text layer",
+            );
+        }
+
+        changes
+    }
+
+    /// One-line description, e.g. "scanned paper, 2 columns, languages: unknown, scan quality: poor".
+    pub fn summary(&self) -> String {
+        let mut parts = vec![
+            format!("{} {}", self.origin, self.kind),
+            format!(
+                "{} column{}",
+                self.columns,
+                if self.columns == 1 { "" } else { "s" }
+            ),
+        ];
+
+        if self.languages.is_empty() {
+            parts.push("languages: unknown (no text layer)".to_string());
+        } else {
+            parts.push(format!("languages: {}", self.languages.join("+")));
+        }
+        if let Some(density) = self.formula_density {
+            parts.push(format!("formulas: {:.1}% of lines", density * 100.0));
+        }
+        if let Some(quality) = self.quality {
+            parts.push(format!(
+                "scan quality: {} (contrast {:.0}, sharpness {:.1})",
+                if quality.is_poor() { "poor" } else { "good" },
+                quality.contrast,
+                quality.sharpness
+            ));
+        }
+        parts.join(", ")
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Origin::BornDigital => "born-digital",
+            Origin::Scanned => "scanned",
+            Origin::Mixed => "partly scanned",
+        })
+    }
+}
+
+impl fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DocumentKind::Paper => "paper",
+            DocumentKind::Book => "book",
+            DocumentKind::Slides => "slides",
+            DocumentKind::Document => "document",
+        })
+    }
+}
+
+/// A config value auto-config can read and write as text, so changes can be reported uniformly.
+trait Setting {
+    fn show(&self) -> String;
+    fn assign(&mut self, value: &str);
+}
+
+impl Setting for u32 {
+    fn show(&self) -> String {
+        self.to_string()
+    }
+    fn assign(&mut self, value: &str) {
+        if let Ok(value) = value.parse() {
+            *self = value;
+        }
+    }
+}
+
+impl Setting for bool {
+    fn show(&self) -> String {
+        self.to_string()
+    }
+    fn assign(&mut self, value: &str) {
+        if let Ok(value) = value.parse() {
+            *self = value;
+        }
+    }
+}
+
+impl Setting for String {
+    fn show(&self) -> String {
+        self.clone()
+    }
+    fn assign(&mut self, value: &str) {
+        *self = value.to_string();
+    }
+}
+
+impl Setting for EngineKind {
+    fn show(&self) -> String {
+        match self {
+            EngineKind::Tesseract => "tesseract",
+            EngineKind::Paddle => "paddle",
+        }
+        .to_string()
+    }
+    fn assign(&mut self, value: &str) {
+        match value {
+            "tesseract" => *self = EngineKind::Tesseract,
+            "paddle" => *self = EngineKind::Paddle,
+            _ => {}
+        }
+    }
+}
+
+/// Up to `SAMPLE_PAGES` pages spread over the document, skipping the cover when possible.
+fn sample_pages(page_count: usize) -> Vec<usize> {
+    if page_count <= SAMPLE_PAGES {
+        return (1..=page_count).collect();
+    }
+    let mut pages: Vec<usize> = (1..=SAMPLE_PAGES)
+        .map(|i| (i * page_count / (SAMPLE_PAGES + 1)).max(1))
+        .collect();
+    pages.dedup();
+    pages
+}
+
+/// Count text columns from vertical runs of ink separated by empty gutters.
+fn count_columns(gray: &GrayImage) -> usize {
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return 1;
+    }
+
+    // Skip headers, titles and footers, which usually span the full width
+    let (top, bottom) = (height / 4, height * 9 / 10);
+    let rows = (bottom - top).max(1) as f32;
+    let inked: Vec<bool> = (0..width)
+        .map(|x| {
+            let ink = (top..bottom)
+                .filter(|&y| gray.get_pixel(x, y)[0] < INK_LUMA)
+                .count();
+            ink as f32 / rows > GUTTER_INK_RATIO
+        })
+        .collect();
+
+    let (Some(first), Some(last)) = (
+        inked.iter().position(|&i| i),
+        inked.iter().rposition(|&i| i),
+    ) else {
+        return 1;
+    };
+
+    let min_gutter = (width as f32 * MIN_GUTTER_RATIO).max(1.0) as usize;
+    let mut columns = 1;
+    let mut gap = 0;
+    for &ink in &inked[first..=last] {
+        if ink {
+            if gap >= min_gutter {
+                columns += 1;
+            }
+            gap = 0;
+        } else {
+            gap += 1;
+        }
+    }
+    columns.min(4)
+}
+
+fn measure_quality(image: &DynamicImage) -> ImageQuality {
+    let gray = image
+        .resize(QUALITY_WIDTH, u32::MAX, FilterType::Triangle)
+        .to_luma8();
+    let (width, height) = gray.dimensions();
+    let pixels = (width as f32 * height as f32).max(1.0);
+
+    let mean = gray.pixels().map(|p| p[0] as f32).sum::<f32>() / pixels;
+    let variance = gray
+        .pixels()
+        .map(|p| (p[0] as f32 - mean).powi(2))
+        .sum::<f32>()
+        / pixels;
+
+    let mut laplacian = 0.0;
+    let mut count = 0usize;
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let at = |dx: i32, dy: i32| {
+                gray.get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32)[0] as f32
+            };
+            laplacian += (4.0 * at(0, 0) - at(-1, 0) - at(1, 0) - at(0, -1) - at(0, 1)).abs();
+            count += 1;
+        }
+    }
+
+    ImageQuality {
+        contrast: variance.sqrt(),
+        sharpness: laplacian / count.max(1) as f32,
+    }
+}
+
+fn measure_formula_density(text: &str) -> f32 {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.is_empty() {
+        return 0.0;
+    }
+    let formulas = lines.iter().filter(|l| math::looks_like_formula(l)).count();
+    formulas as f32 / lines.len() as f32
+}
+
+/// Tesseract language codes for the scripts in `text`, most frequent first.
+fn text_languages(text: &str) -> Vec<&'static str> {
+    let (mut han, mut kana, mut hangul, mut latin) = (0usize, 0usize, 0usize, 0usize);
+    for c in text.chars() {
+        match c as u32 {
+            0x3040..=0x30FF => kana += 1,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF => han += 1,
+            0x1100..=0x11FF | 0xAC00..=0xD7AF => hangul += 1,
+            code if code < 0x250 && c.is_alphabetic() => latin += 1,
+            _ => {}
+        }
+    }
+
+    let total = (han + kana + hangul + latin) as f32;
+    if total == 0.0 {
+        return Vec::new();
+    }
+    let present = |count: usize| count as f32 / total >= MIN_SCRIPT_SHARE;
+
+    let mut found: Vec<(&'static str, usize)> = Vec::new();
+    if present(kana) {
+        // Japanese mixes kana with kanji
+        found.push(("jpn", kana + han));
+    } else if present(han) {
+        found.push(("chi_sim", han));
+    }
+    if present(hangul) {
+        found.push(("kor", hangul));
+    }
+    if present(latin) {
+        let latin_text: String = text
+            .chars()
+            .filter(|c| (*c as u32) < 0x250)
+            .take(4000)
+            .collect();
+        let lang = LanguageDetector::new()
+            .detect(&latin_text)
+            .filter(|l| !matches!(*l, "chi_sim" | "jpn" | "kor"))
+            .unwrap_or("eng");
+        found.push((lang, latin));
+    }
+
+    found.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+    found.into_iter().map(|(lang, _)| lang).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// A white page with solid ink between each pair of x bounds.
+    fn page_with_ink(width: u32, height: u32, spans: &[(u32, u32)]) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, _| {
+            if spans.iter().any(|&(from, to)| (from..to).contains(&x)) {
+                Luma([0])
+            } else {
+                Luma([255])
+            }
+        })
+    }
+
+    fn analysis() -> Analysis {
+        Analysis {
+            sampled: vec![1, 2, 3],
+            origin: Origin::BornDigital,
+            kind: DocumentKind::Paper,
+            columns: 2,
+            languages: vec!["eng"],
+            formula_density: Some(0.0),
+            quality: None,
+        }
+    }
+
+    #[test]
+    fn samples_every_page_of_short_documents() {
+        assert_eq!(sample_pages(0), Vec::<usize>::new());
+        assert_eq!(sample_pages(3), vec![1, 2, 3]);
+        assert_eq!(sample_pages(SAMPLE_PAGES), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn spreads_samples_over_long_documents() {
+        assert_eq!(sample_pages(6), vec![1, 2, 3, 4, 5]);
+        assert_eq!(sample_pages(120), vec![20, 40, 60, 80, 100]);
+        for page_count in [7, 50, 1000] {
+            let pages = sample_pages(page_count);
+            assert_eq!(pages.len(), SAMPLE_PAGES);
+            assert!(pages.windows(2).all(|w| w[0] < w[1]));
+            assert!(pages.iter().all(|&p| (1..=page_count).contains(&p)));
+        }
+    }
+
+    #[test]
+    fn counts_columns_separated_by_gutters() {
+        assert_eq!(count_columns(&page_with_ink(100, 100, &[])), 1);
+        assert_eq!(count_columns(&page_with_ink(100, 100, &[(10, 90)])), 1);
+        assert_eq!(
+            count_columns(&page_with_ink(100, 100, &[(10, 45), (55, 90)])),
+            2
+        );
+        assert_eq!(
+            count_columns(&page_with_ink(
+                200,
+                100,
+                &[(10, 50), (60, 100), (110, 150), (160, 190), (195, 198)]
+            )),
+            4
+        );
+    }
+
+    #[test]
+    fn ignores_gaps_narrower_than_a_gutter() {
+        // One pixel is below 2% of the width
+        assert_eq!(
+            count_columns(&page_with_ink(100, 100, &[(10, 50), (51, 90)])),
+            1
+        );
+    }
+
+    #[test]
+    fn ignores_full_width_headers() {
+        let mut page = page_with_ink(100, 100, &[(10, 45), (55, 90)]);
+        for x in 0..100 {
+            for y in 0..10 {
+                page.put_pixel(x, y, Luma([0]));
+            }
+        }
+        assert_eq!(count_columns(&page), 2);
+    }
+
+    #[test]
+    fn finds_no_languages_without_letters() {
+        assert!(text_languages("").is_empty());
+        assert!(text_languages("12 + 34 = 46 ...").is_empty());
+    }
+
+    #[test]
+    fn detects_cjk_scripts() {
+        assert_eq!(
+            text_languages("这是一个用于测试的中文句子。"),
+            vec!["chi_sim"]
+        );
+        assert_eq!(text_languages("これは日本語のテストです。"), vec!["jpn"]);
+        assert_eq!(text_languages("이것은 한국어 문장입니다."), vec!["kor"]);
+    }
+
+    #[test]
+    fn orders_languages_by_frequency() {
+        let text = "这是一个用于测试的中文句子，其中包含少量英文单词。 \
+                    The quick brown fox.";
+        assert_eq!(text_languages(text), vec!["chi_sim", "eng"]);
+
+        let text = "The quick brown fox jumps over the lazy dog while the \
+                    farmer watches from the porch. 中文测试句子";
+        assert_eq!(text_languages(text), vec!["eng", "chi_sim"]);
+    }
+
+    #[test]
+    fn tune_adjusts_defaults_to_the_document() {
+        let mut config = Config::default();
+        let changes = analysis().tune(&mut config, &BTreeSet::new());
+
+        assert!(!config.preprocess);
+        assert!(!config.math_ocr);
+        assert_eq!(config.languages, "eng");
+        assert!(!config.detect_language);
+        assert!(changes.iter().all(|c| c.applied));
+        assert!(!changes.iter().any(|c| c.key == "dpi" || c.key == "layout"));
+    }
+
+    #[test]
+    fn tune_leaves_explicit_keys_alone() {
+        let mut config = Config::default();
+        let explicit = BTreeSet::from(["math_ocr".to_string(), "preprocess".to_string()]);
+        let changes = analysis().tune(&mut config, &explicit);
+
+        assert!(config.preprocess);
+        assert!(config.math_ocr);
+        // Math OCR stayed on, so its language is kept
+        assert_eq!(config.languages, "eng+equ");
+
+        let math = changes.iter().find(|c| c.key == "math_ocr").unwrap();
+        assert!(!math.applied);
+        assert_eq!((math.from.as_str(), math.to.as_str()), ("true", "false"));
+        let languages = changes.iter().find(|c| c.key == "languages").unwrap();
+        assert!(languages.applied);
+    }
+}
@@ -0,0 +1,440 @@
+use crate::ocr_engine::{BoundingBox, OcrBlock};
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A link covering more than this fraction of a block links the whole block.
+const WHOLE_BLOCK_COVERAGE: f32 = 0.6;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LinkTarget {
+    Uri(String),
+    /// 1-based page number inside the same document
+    Page(usize),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PageLink {
+    pub bbox: BoundingBox,
+    pub target: LinkTarget,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AnnotationKind {
+    Highlight,
+    Underline,
+    Strikeout,
+    Comment,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PageAnnotation {
+    pub kind: AnnotationKind,
+    pub bbox: BoundingBox,
+    pub contents: Option<String>,
+    pub author: Option<String>,
+}
+
+/// Convert a rectangle in PDF points (origin bottom-left) to pixels (origin top-left).
+pub fn pdf_rect_to_bbox(rect: &PdfRect, page_height: f32, scale: f32) -> BoundingBox {
+    BoundingBox {
+        x: (rect.left().value * scale).max(0.0) as u32,
+        y: ((page_height - rect.top().value) * scale).max(0.0) as u32,
+        width: (rect.width().value * scale).max(0.0) as u32,
+        height: (rect.height().value * scale).max(0.0) as u32,
+    }
+}
+
+/// Read URI and internal GoTo link annotations, converted to pixel coordinates.
+pub fn read_links(page: &PdfPage, scale: f32) -> Vec<PageLink> {
+    let page_height = page.height().value;
+    page.links()
+        .iter()
+        .filter_map(|link| {
+            let rect = link.rect().ok()?;
+            let target = match link.destination() {
+                Some(destination) => LinkTarget::Page(destination.page_index().ok()? as usize + 1),
+                None => link_action_target(&link.action()?)?,
+            };
+            Some(PageLink {
+                bbox: pdf_rect_to_bbox(&rect, page_height, scale),
+                target,
+            })
+        })
+        .collect()
+}
+
+fn link_action_target(action: &PdfAction) -> Option<LinkTarget> {
+    if let Some(uri) = action.as_uri_action() {
+        return uri.uri().ok().map(LinkTarget::Uri);
+    }
+    let destination = action.as_local_destination_action()?.destination().ok()?;
+    Some(LinkTarget::Page(
+        destination.page_index().ok()? as usize + 1,
+    ))
+}
+
+/// Read text markup and sticky-note annotations, converted to pixel coordinates.
+pub fn read_annotations(page: &PdfPage, scale: f32) -> Vec<PageAnnotation> {
+    let page_height = page.height().value;
+    page.annotations()
+        .iter()
+        .filter_map(|annotation| {
+            let kind = match annotation.annotation_type() {
+                PdfPageAnnotationType::Highlight => AnnotationKind::Highlight,
+                PdfPageAnnotationType::Underline | PdfPageAnnotationType::Squiggly => {
+                    AnnotationKind::Underline
+                }
+                PdfPageAnnotationType::Strikeout => AnnotationKind::Strikeout,
+                PdfPageAnnotationType::Text | PdfPageAnnotationType::FreeText => {
+                    AnnotationKind::Comment
+                }
+                _ => return None,
+            };
+            let contents = annotation
+                .contents()
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty());
+            // A sticky note without text carries no information
+            if kind == AnnotationKind::Comment && contents.is_none() {
+                return None;
+            }
+            let rect = annotation.bounds().ok()?;
+            Some(PageAnnotation {
+                kind,
+                bbox: pdf_rect_to_bbox(&rect, page_height, scale),
+                contents,
+                author: annotation
+                    .creator()
+                    .map(|a| a.trim().to_string())
+                    .filter(|a| !a.is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// Wrap the linked part of a block's text in Markdown link syntax.
+///
+/// OCR blocks have no character positions, so a link covering only part of a block is
+/// mapped onto the words under its horizontal extent.
+pub fn apply_links(
+    text: &str,
+    bbox: &BoundingBox,
+    links: &[PageLink],
+    page_href: impl Fn(usize) -> String,
+) -> String {
+    let Some((link, coverage)) = links
+        .iter()
+        .map(|l| (l, overlap_ratio(bbox, &l.bbox)))
+        .filter(|(_, coverage)| *coverage > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+    else {
+        return text.to_string();
+    };
+
+    let href = match &link.target {
+        // Script and data links stay plain text
+        LinkTarget::Uri(uri) if !is_safe_uri(uri) => return text.to_string(),
+        LinkTarget::Uri(uri) => uri_destination(uri),
+        LinkTarget::Page(page) => page_href(*page),
+    };
+
+    if coverage >= WHOLE_BLOCK_COVERAGE || text.contains('\n') {
+        return format!("[{}]({})", escape_link_text(text), href);
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let width = bbox.width.max(1) as f32;
+    let to_index = |x: u32| {
+        let ratio = (x.saturating_sub(bbox.x) as f32 / width).clamp(0.0, 1.0);
+        (ratio * chars.len() as f32).round() as usize
+    };
+    let mut start = to_index(link.bbox.x).min(chars.len());
+    let mut end = to_index(link.bbox.x + link.bbox.width).min(chars.len());
+
+    // Snap to word boundaries
+    while start > 0 && !chars[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    while end < chars.len() && !chars[end].is_whitespace() {
+        end += 1;
+    }
+    if start >= end {
+        return text.to_string();
+    }
+
+    let before: String = chars[..start].iter().collect();
+    let linked: String = chars[start..end].iter().collect();
+    let after: String = chars[end..].iter().collect();
+    format!(
+        "{}[{}]({}){}",
+        before,
+        escape_link_text(&linked),
+        href,
+        after
+    )
+}
+
+/// Assign each annotation to the block it overlaps most, or the vertically nearest block.
+pub fn assign_annotations(
+    blocks: &[OcrBlock],
+    annotations: &[PageAnnotation],
+) -> Vec<Option<usize>> {
+    annotations
+        .iter()
+        .map(|annotation| {
+            let candidates = blocks
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, block)| block.bbox.as_ref().map(|bbox| (idx, bbox)));
+
+            let overlapping = candidates
+                .clone()
+                .map(|(idx, bbox)| (idx, overlap_ratio(bbox, &annotation.bbox)))
+                .filter(|(_, ratio)| *ratio > 0.0)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(idx, _)| idx);
+
+            overlapping
+                .or_else(|| {
+                    let center = annotation.bbox.y + annotation.bbox.height / 2;
+                    candidates
+                        .min_by_key(|(_, bbox)| center.abs_diff(bbox.y + bbox.height / 2))
+                        .map(|(idx, _)| idx)
+                })
+                .or_else(|| (!blocks.is_empty()).then_some(0))
+        })
+        .collect()
+}
+
+/// Footnote body for an annotation, e.g. "**Highlight** (Alice): check this".
+pub fn describe(annotation: &PageAnnotation) -> String {
+    let kind = match annotation.kind {
+        AnnotationKind::Highlight => "Highlight",
+        AnnotationKind::Underline => "Underline",
+        AnnotationKind::Strikeout => "Strikeout",
+        AnnotationKind::Comment => "Comment",
+    };
+    let mut note = format!("**{}**", kind);
+    if let Some(author) = &annotation.author {
+        note.push_str(&format!(" ({})", author));
+    }
+    if let Some(contents) = &annotation.contents {
+        note.push_str(": ");
+        note.push_str(&contents.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    note
+}
+
+/// Fraction of `block` covered by `other`.
+fn overlap_ratio(block: &BoundingBox, other: &BoundingBox) -> f32 {
+    let x0 = block.x.max(other.x);
+    let y0 = block.y.max(other.y);
+    let x1 = (block.x + block.width).min(other.x + other.width);
+    let y1 = (block.y + block.height).min(other.y + other.height);
+    if x1 <= x0 || y1 <= y0 {
+        return 0.0;
+    }
+    let area = (block.width as f32 * block.height as f32).max(1.0);
+    ((x1 - x0) as f32 * (y1 - y0) as f32) / area
+}
+
+/// Whether `uri` is relative or uses a scheme that is safe to link to (http, https, mailto).
+pub fn is_safe_uri(uri: &str) -> bool {
+    // Browsers ignore whitespace and control characters inside the scheme
+    let cleaned: String = uri
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let scheme = cleaned
+        .find([':', '/', '?', '#'])
+        .filter(|&idx| cleaned[idx..].starts_with(':'))
+        .map(|idx| &cleaned[..idx]);
+    matches!(scheme, None | Some("http" | "https" | "mailto"))
+}
+
+/// A Markdown link destination for `uri`: wrapped in `<...>`, with the characters that
+/// could end it early percent-encoded.
+fn uri_destination(uri: &str) -> String {
+    let mut destination = String::from("<");
+    for c in uri.chars() {
+        match c {
+            ' ' | '<' | '>' | '(' | ')' | '\\' => {
+                destination.push_str(&format!("%{:02X}", c as u32))
+            }
+            c if c.is_control() => {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    destination.push_str(&format!("%{:02X}", byte));
+                }
+            }
+            c => destination.push(c),
+        }
+    }
+    destination.push('>');
+    destination
+}
+
+fn escape_link_text(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr_engine::BlockType;
+
+    fn bbox(x: u32, y: u32, width: u32, height: u32) -> BoundingBox {
+        BoundingBox {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn uri_link(bbox: BoundingBox, uri: &str) -> PageLink {
+        PageLink {
+            bbox,
+            target: LinkTarget::Uri(uri.to_string()),
+        }
+    }
+
+    fn block(bbox: Option<BoundingBox>) -> OcrBlock {
+        OcrBlock {
+            text: "text".to_string(),
+            confidence: 1.0,
+            bbox,
+            block_type: BlockType::Text,
+            language: None,
+            image: None,
+        }
+    }
+
+    fn annotation(bbox: BoundingBox) -> PageAnnotation {
+        PageAnnotation {
+            kind: AnnotationKind::Highlight,
+            bbox,
+            contents: None,
+            author: None,
+        }
+    }
+
+    fn page_href(page: usize) -> String {
+        format!("#page-{}", page)
+    }
+
+    #[test]
+    fn measures_overlap_as_a_fraction_of_the_block() {
+        let block = bbox(0, 0, 100, 10);
+        assert_eq!(overlap_ratio(&block, &bbox(0, 0, 100, 10)), 1.0);
+        assert_eq!(overlap_ratio(&block, &bbox(50, 0, 100, 10)), 0.5);
+        assert_eq!(overlap_ratio(&block, &bbox(0, 5, 25, 100)), 0.125);
+        assert_eq!(overlap_ratio(&block, &bbox(100, 0, 10, 10)), 0.0);
+        assert_eq!(overlap_ratio(&block, &bbox(0, 20, 100, 10)), 0.0);
+    }
+
+    #[test]
+    fn links_whole_block_when_mostly_covered() {
+        let links = [uri_link(bbox(0, 0, 90, 10), "https://example.com/a b")];
+        assert_eq!(
+            apply_links("see [1] here", &bbox(0, 0, 100, 10), &links, page_href),
+            "[see \\[1\\] here](<https://example.com/a%20b>)"
+        );
+    }
+
+    #[test]
+    fn links_words_under_a_partial_link() {
+        let links = [PageLink {
+            bbox: bbox(60, 0, 40, 10),
+            target: LinkTarget::Page(4),
+        }];
+        assert_eq!(
+            apply_links(
+                "as shown in Figure 2",
+                &bbox(0, 0, 100, 10),
+                &links,
+                page_href
+            ),
+            "as shown in [Figure 2](#page-4)"
+        );
+    }
+
+    #[test]
+    fn leaves_text_without_overlapping_links() {
+        let links = [uri_link(bbox(0, 50, 100, 10), "https://example.com")];
+        assert_eq!(
+            apply_links("plain", &bbox(0, 0, 100, 10), &links, page_href),
+            "plain"
+        );
+    }
+
+    #[test]
+    fn escapes_link_destinations() {
+        let links = [uri_link(
+            bbox(0, 0, 100, 10),
+            "https://en.wikipedia.org/wiki/Rust_(language)<x>",
+        )];
+        assert_eq!(
+            apply_links("Rust", &bbox(0, 0, 100, 10), &links, page_href),
+            "[Rust](<https://en.wikipedia.org/wiki/Rust_%28language%29%3Cx%3E>)"
+        );
+    }
+
+    #[test]
+    fn drops_unsafe_link_schemes() {
+        for uri in [
+            "javascript:alert(1)",
+            " JavaScript:alert(1)",
+            "java\tscript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+        ] {
+            let links = [uri_link(bbox(0, 0, 100, 10), uri)];
+            assert_eq!(
+                apply_links("click", &bbox(0, 0, 100, 10), &links, page_href),
+                "click",
+                "{uri}"
+            );
+        }
+        assert!(is_safe_uri("mailto:someone@example.com"));
+        assert!(is_safe_uri("docs/page.html#intro"));
+        assert!(is_safe_uri("/path?q=a:b"));
+    }
+
+    #[test]
+    fn assigns_annotations_to_the_most_overlapped_block() {
+        let blocks = [
+            block(Some(bbox(0, 0, 100, 20))),
+            block(Some(bbox(0, 30, 100, 20))),
+        ];
+        let annotations = [annotation(bbox(0, 15, 100, 20))];
+        assert_eq!(assign_annotations(&blocks, &annotations), vec![Some(1)]);
+    }
+
+    #[test]
+    fn assigns_annotations_to_the_nearest_block_without_overlap() {
+        let blocks = [
+            block(None),
+            block(Some(bbox(0, 0, 100, 20))),
+            block(Some(bbox(0, 100, 100, 20))),
+        ];
+        let annotations = [
+            annotation(bbox(200, 90, 10, 10)),
+            annotation(bbox(200, 10, 10, 10)),
+        ];
+        assert_eq!(
+            assign_annotations(&blocks, &annotations),
+            vec![Some(2), Some(1)]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_first_block() {
+        let annotations = [annotation(bbox(0, 0, 10, 10))];
+        assert_eq!(
+            assign_annotations(&[block(None)], &annotations),
+            vec![Some(0)]
+        );
+        assert_eq!(assign_annotations(&[], &annotations), vec![None]);
+    }
+}
@@ -0,0 +1,369 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rust_ocr2md::config::ErrorPolicy;
+use rust_ocr2md::error::OcrError;
+use rust_ocr2md::manifest::Manifest;
+use rust_ocr2md::page_source::IMAGE_EXTENSIONS;
+use rust_ocr2md::progress::Event;
+use rust_ocr2md::report::{DocumentReport, RunReport, Status};
+use rust_ocr2md::{ConvertOptions, Converter, Document};
+
+use crate::console::{self, ProgressMode};
+use crate::interrupt;
+use crate::PipelineArgs;
+
+#[derive(Args)]
+pub struct BatchArgs {
+    /// Input directories, files or glob patterns (e.g. "scans/**/*.pdf")
+    #[arg(required = true)]
+    inputs: Vec<String>,
+
+    /// Output directory; the input directory structure is mirrored below it
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Convert even when the output is newer than the input
+    #[arg(long, default_value = "false")]
+    force: bool,
+
+    #[command(flatten)]
+    pub(crate) pipeline: PipelineArgs,
+}
+
+/// One input file and where its Markdown goes.
+struct BatchItem {
+    input: PathBuf,
+    output: PathBuf,
+}
+
+enum Outcome {
+    Converted { pages: usize, converted: usize },
+    Skipped,
+    Failed(String),
+}
+
+pub fn run(args: &BatchArgs) -> Result<()> {
+    let start = Instant::now();
+
+    eprintln!("🚀 RustOCR2md - High-performance PDF OCR Tool");
+    eprintln!("================================================");
+
+    let items = collect_items(&args.inputs, &args.out)?;
+    if items.is_empty() {
+        anyhow::bail!("No PDF or image files found in: {}", args.inputs.join(", "));
+    }
+    eprintln!("📚 Files: {}", items.len());
+
+    // Models are loaded once per backend and shared by every file
+    let loaded = args.pipeline.load_config()?;
+    crate::print_config_sources(&loaded);
+    let base_config = &loaded.config;
+    eprintln!(
+        "⚙️  Config: {} DPI, {} threads, Languages: {}, Engine: {:?}",
+        base_config.dpi, base_config.threads, base_config.languages, base_config.engine
+    );
+    let converter = args.pipeline.converter(&loaded)?;
+    converter.load_engine()?;
+
+    let mut rows = Vec::with_capacity(items.len());
+    let mut reports = Vec::with_capacity(items.len());
+    for (idx, item) in items.iter().enumerate() {
+        eprintln!(
+            "\n[{}/{}] 📄 {}",
+            idx + 1,
+            items.len(),
+            item.input.display()
+        );
+        let file_start = Instant::now();
+        let outcome = if !args.force && is_up_to_date(&item.input, &item.output) {
+            eprintln!("⏭️  Up to date: {}", item.output.display());
+            reports.push(DocumentReport::not_converted(
+                &item.input,
+                Status::Skipped,
+                None,
+                file_start.elapsed(),
+            ));
+            Outcome::Skipped
+        } else {
+            match convert_item(item, &converter, args.pipeline.resume) {
+                Ok(document) => {
+                    crate::print_failures(&document.failures, args.pipeline.progress);
+                    reports.push(document.report);
+                    Outcome::Converted {
+                        pages: document.selected,
+                        converted: document.pages.len(),
+                    }
+                }
+                Err(e) => {
+                    reports.push(DocumentReport::not_converted(
+                        &item.input,
+                        Status::Failed,
+                        Some(format!("{:#}", e)),
+                        file_start.elapsed(),
+                    ));
+                    if args.pipeline.progress == ProgressMode::Json {
+                        console::emit(&Event::Error {
+                            message: format!("{}: {:#}", item.input.display(), e),
+                        });
+                    } else {
+                        eprintln!("❌ {}", e);
+                    }
+                    Outcome::Failed(e.to_string())
+                }
+            }
+        };
+        let failed = matches!(outcome, Outcome::Failed(_));
+        rows.push((item, outcome, file_start.elapsed()));
+        if failed && base_config.on_error == ErrorPolicy::Abort {
+            eprintln!(
+                "🛑 Stopping batch (--on-error abort), {} file(s) not processed",
+                items.len() - rows.len()
+            );
+            break;
+        }
+        if interrupt::requested() {
+            eprintln!(
+                "⏸️  Stopping batch after Ctrl-C, {} file(s) not processed",
+                items.len() - rows.len()
+            );
+            break;
+        }
+    }
+
+    print_summary(&rows, start.elapsed());
+
+    if let Some(report_path) = &args.pipeline.report {
+        RunReport::new(reports, start.elapsed()).write(report_path)?;
+        eprintln!("📊 Report: {}", report_path.display());
+    }
+
+    // A file that converted no pages counts as failed; one that lost some pages as incomplete
+    let count = |f: fn(&Outcome) -> bool| rows.iter().filter(|(_, o, _)| f(o)).count();
+    let failed = count(|o| {
+        matches!(
+            o,
+            Outcome::Failed(_) | Outcome::Converted { converted: 0, .. }
+        )
+    });
+    let incomplete = count(
+        |o| matches!(o, Outcome::Converted { pages, converted } if *converted > 0 && converted < pages),
+    );
+    let converted = count(|o| matches!(o, Outcome::Converted { converted, .. } if *converted > 0));
+    let unprocessed = items.len() - rows.len();
+    if interrupt::requested() {
+        return Err(OcrError::Interrupted(format!(
+            "{} file(s) incomplete, {} not processed; run again with --resume",
+            incomplete, unprocessed
+        ))
+        .into());
+    }
+    if failed > 0 && converted == 0 {
+        return Err(OcrError::TotalFailure(format!(
+            "{} of {} files failed and none converted",
+            failed,
+            items.len()
+        ))
+        .into());
+    }
+    if failed + incomplete + unprocessed > 0 {
+        return Err(OcrError::PartialFailure(format!(
+            "{} of {} files failed, {} incomplete, {} not processed",
+            failed,
+            items.len(),
+            incomplete,
+            unprocessed
+        ))
+        .into());
+    }
+
+    if args.pipeline.interactive() {
+        crate::wait_for_enter();
+    }
+    Ok(())
+}
+
+fn convert_item(item: &BatchItem, converter: &Converter, resume: bool) -> Result<Document> {
+    let output_dir = item.output.parent().unwrap_or_else(|| Path::new(""));
+    std::fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+
+    // Files sharing an output directory get their own asset folder below the configured one
+    let stem = item
+        .output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let options = ConvertOptions {
+        pages: None,
+        output: Some(item.output.clone()),
+        stdout: false,
+        assets_dir: Some(output_dir.join(&converter.config().assets_dir).join(stem)),
+        resume,
+        json: None,
+    };
+    converter.convert(&item.input, &options)
+}
+
+fn print_summary(rows: &[(&BatchItem, Outcome, Duration)], total: Duration) {
+    eprintln!("\n📊 Batch summary");
+    eprintln!("{:<9} {:>7} {:>9}  File", "Status", "Pages", "Time");
+    for (item, outcome, elapsed) in rows {
+        let (status, pages, detail) = match outcome {
+            Outcome::Converted { pages, converted } if converted < pages => {
+                ("partial", format!("{}/{}", converted, pages), String::new())
+            }
+            Outcome::Converted { pages, .. } => ("ok", pages.to_string(), String::new()),
+            Outcome::Skipped => ("skipped", "-".to_string(), String::new()),
+            Outcome::Failed(e) => ("failed", "-".to_string(), format!(" ({})", e)),
+        };
+        eprintln!(
+            "{:<9} {:>7} {:>8.1}s  {}{}",
+            status,
+            pages,
+            elapsed.as_secs_f64(),
+            item.input.display(),
+            detail
+        );
+    }
+
+    let count = |f: fn(&Outcome) -> bool| rows.iter().filter(|(_, o, _)| f(o)).count();
+    eprintln!(
+        "\n✨ Converted: {}, skipped: {}, failed: {} in {:.2}s",
+        count(|o| matches!(o, Outcome::Converted { .. })),
+        count(|o| matches!(o, Outcome::Skipped)),
+        count(|o| matches!(o, Outcome::Failed(_))),
+        total.as_secs_f64()
+    );
+}
+
+/// Expand directories (recursively) and glob patterns into input/output pairs.
+fn collect_items(inputs: &[String], out: &Path) -> Result<Vec<BatchItem>> {
+    let mut seen = BTreeSet::new();
+    let mut items = Vec::new();
+
+    for spec in inputs {
+        let path = Path::new(spec);
+        let files: Vec<(PathBuf, PathBuf)> = if path.is_dir() {
+            walk_dir(path)?
+                .into_iter()
+                .map(|file| {
+                    let relative = file.strip_prefix(path).unwrap_or(&file).to_path_buf();
+                    (file, relative)
+                })
+                .collect()
+        } else if spec.contains(['*', '?', '[']) {
+            let base = glob_base(spec);
+            glob::glob(spec)
+                .with_context(|| format!("Invalid glob pattern: {}", spec))?
+                .filter_map(|entry| entry.ok())
+                .filter(|file| file.is_file() && is_supported(file))
+                .map(|file| {
+                    let relative = relative_to(&file, &base);
+                    (file, relative)
+                })
+                .collect()
+        } else if path.is_file() {
+            let name = PathBuf::from(path.file_name().unwrap_or(path.as_os_str()));
+            vec![(path.to_path_buf(), name)]
+        } else {
+            anyhow::bail!("Input does not exist: {}", spec);
+        };
+
+        for (input, relative) in files {
+            if seen.insert(input.clone()) {
+                items.push(BatchItem {
+                    input,
+                    output: out.join(relative).with_extension("md"),
+                });
+            }
+        }
+    }
+
+    // a.pdf and a.png in one folder would both write a.md; they become a.pdf.md and a.png.md
+    let mut counts: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    for item in &items {
+        *counts.entry(item.output.clone()).or_default() += 1;
+    }
+    for item in &mut items {
+        if counts[&item.output] > 1 {
+            let name = item.input.file_name().unwrap_or(item.input.as_os_str());
+            item.output = item
+                .output
+                .with_file_name(format!("{}.md", name.to_string_lossy()));
+        }
+    }
+    let mut outputs: BTreeMap<&Path, &Path> = BTreeMap::new();
+    for item in &items {
+        if let Some(other) = outputs.insert(&item.output, &item.input) {
+            anyhow::bail!(
+                "{} and {} would both be written to {}",
+                other.display(),
+                item.input.display(),
+                item.output.display()
+            );
+        }
+    }
+
+    Ok(items)
+}
+
+fn walk_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+
+    let mut files = Vec::new();
+    for entry in entries {
+        if entry.is_dir() {
+            files.extend(walk_dir(&entry)?);
+        } else if is_supported(&entry) {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+pub(crate) fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|ext| ext == "pdf" || IMAGE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// The directory part of a glob before the first wildcard, e.g. "scans" for "scans/**/*.pdf".
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect()
+}
+
+fn relative_to(file: &Path, base: &Path) -> PathBuf {
+    match file.strip_prefix(base) {
+        Ok(relative)
+            if relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_))) =>
+        {
+            relative.to_path_buf()
+        }
+        _ => PathBuf::from(file.file_name().unwrap_or(file.as_os_str())),
+    }
+}
+
+/// Output newer than the input, and not left unfinished by an interrupted run.
+fn is_up_to_date(input: &Path, output: &Path) -> bool {
+    if Manifest::path_for(output).exists() {
+        return false;
+    }
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(input), modified(output)) {
+        (Some(input_time), Some(output_time)) => output_time >= input_time,
+        _ => false,
+    }
+}
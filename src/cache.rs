@@ -1,38 +1,122 @@
 use crate::config::CacheConfig;
 use crate::pdf_processor::PageResult;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use image::DynamicImage;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Size of each cache directory as last listed plus what this process wrote since, so the
+/// size limit does not list the whole cache after every document.
+static KNOWN_SIZES: Mutex<BTreeMap<PathBuf, u64>> = Mutex::new(BTreeMap::new());
+
+/// The size limit prunes down to this share of it, so the next documents stay under it.
+const PRUNE_TARGET_PERCENT: u64 = 90;
+
+/// Which document page an entry was made for, kept in `index/<hash>.json`.
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    document: PathBuf,
+    page: usize,
+}
+
+/// The cached files of one page: OCR result and preprocessed image share its hash.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub hash: String,
+    /// Document and page the entry was made for; unknown for entries written before
+    /// the cache kept an index
+    pub document: Option<PathBuf>,
+    pub page: Option<usize>,
+    /// Cached `PageResult`, readable by `render`
+    pub ocr_result: Option<PathBuf>,
+    pub ocr_bytes: u64,
+    pub preprocess_bytes: u64,
+    /// Total size including the index file
+    pub size: u64,
+    /// When the entry was last written or read
+    pub last_used: SystemTime,
+    files: Vec<PathBuf>,
+}
+
+impl CacheEntry {
+    fn new(hash: String) -> Self {
+        Self {
+            hash,
+            document: None,
+            page: None,
+            ocr_result: None,
+            ocr_bytes: 0,
+            preprocess_bytes: 0,
+            size: 0,
+            last_used: SystemTime::UNIX_EPOCH,
+            files: Vec::new(),
+        }
+    }
+
+    /// An index file left behind after its page's files were removed.
+    fn is_orphan(&self) -> bool {
+        self.ocr_result.is_none() && self.preprocess_bytes == 0
+    }
+}
+
+/// What a clean-up removed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Removed {
+    pub entries: usize,
+    pub bytes: u64,
+}
 
 pub struct CacheManager {
     config: CacheConfig,
     ocr_dir: PathBuf,
     preprocess_dir: PathBuf,
+    index_dir: PathBuf,
+    /// Document the pages being cached belong to, recorded in the index
+    document: Option<PathBuf>,
 }
 
 impl CacheManager {
     pub fn new(config: &CacheConfig) -> Result<Self> {
-        let ocr_dir = config.dir.join("ocr");
-        let preprocess_dir = config.dir.join("preprocess");
-
+        let cache = Self::open(config);
         if config.enabled {
-            fs::create_dir_all(&ocr_dir)?;
-            fs::create_dir_all(&preprocess_dir)?;
+            fs::create_dir_all(&cache.ocr_dir)?;
+            fs::create_dir_all(&cache.preprocess_dir)?;
+            fs::create_dir_all(&cache.index_dir)?;
         }
+        Ok(cache)
+    }
 
-        Ok(Self {
+    /// The cache at `config.dir` for listing and clean-up; nothing is created.
+    pub fn open(config: &CacheConfig) -> Self {
+        Self {
             config: config.clone(),
-            ocr_dir,
-            preprocess_dir,
-        })
+            ocr_dir: config.dir.join("ocr"),
+            preprocess_dir: config.dir.join("preprocess"),
+            index_dir: config.dir.join("index"),
+            document: None,
+        }
+    }
+
+    /// Record `document` as the source of the pages stored from now on.
+    pub fn with_document(mut self, document: &Path) -> Self {
+        // Absolute, so the listing is the same from any working directory
+        self.document = Some(fs::canonicalize(document).unwrap_or_else(|_| document.into()));
+        self
     }
 
     pub fn enabled(&self) -> bool {
         self.config.enabled
     }
 
+    pub fn dir(&self) -> &Path {
+        &self.config.dir
+    }
+
     pub fn make_hash(
         &self,
         pdf_path: &Path,
@@ -61,6 +145,9 @@ impl CacheManager {
         }
         let data = fs::read_to_string(&path)?;
         let page: PageResult = serde_json::from_str(&data)?;
+        touch(&path);
+        // The index only serves listings; a read-only cache is still usable
+        let _ = self.record(hash, page.page_num);
         Ok(Some(page))
     }
 
@@ -70,8 +157,9 @@ impl CacheManager {
         }
         let path = self.ocr_dir.join(format!("{hash}.json"));
         let data = serde_json::to_string(page)?;
-        fs::write(path, data)?;
-        Ok(())
+        fs::write(path, &data)?;
+        self.grew(data.len() as u64);
+        self.record(hash, page.page_num)
     }
 
     pub fn load_preprocessed(&self, hash: &str) -> Result<Option<DynamicImage>> {
@@ -82,16 +170,323 @@ impl CacheManager {
         if !path.exists() {
             return Ok(None);
         }
-        let img = image::open(path)?;
+        let img = image::open(&path)?;
+        touch(&path);
         Ok(Some(img))
     }
 
-    pub fn store_preprocessed(&self, hash: &str, image: &DynamicImage) -> Result<()> {
+    pub fn store_preprocessed(
+        &self,
+        hash: &str,
+        page_num: usize,
+        image: &DynamicImage,
+    ) -> Result<()> {
         if !self.config.enabled || !self.config.preprocess {
             return Ok(());
         }
         let path = self.preprocess_dir.join(format!("{hash}.png"));
-        image.save(path)?;
+        image.save(&path)?;
+        self.grew(fs::metadata(&path).map_or(0, |m| m.len()));
+        self.record(hash, page_num)
+    }
+
+    fn record(&self, hash: &str, page_num: usize) -> Result<()> {
+        let Some(document) = &self.document else {
+            return Ok(());
+        };
+        let path = self.index_dir.join(format!("{hash}.json"));
+        if path.exists() {
+            return Ok(());
+        }
+        let entry = IndexEntry {
+            document: document.clone(),
+            page: page_num,
+        };
+        let data = serde_json::to_string(&entry)?;
+        fs::write(path, &data)?;
+        self.grew(data.len() as u64);
         Ok(())
     }
+
+    fn grew(&self, bytes: u64) {
+        if let Some(size) = KNOWN_SIZES.lock().unwrap().get_mut(&self.config.dir) {
+            *size += bytes;
+        }
+    }
+
+    /// Every cached page, least recently used first.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut entries: BTreeMap<String, CacheEntry> = BTreeMap::new();
+        let dirs = [
+            (&self.ocr_dir, FileKind::Ocr),
+            (&self.preprocess_dir, FileKind::Preprocess),
+            (&self.index_dir, FileKind::Index),
+        ];
+        for (dir, kind) in dirs {
+            for (path, metadata) in files_in(dir)? {
+                let Some(hash) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+                    continue;
+                };
+                let entry = entries
+                    .entry(hash.clone())
+                    .or_insert_with(|| CacheEntry::new(hash));
+                entry.size += metadata.len();
+                match kind {
+                    FileKind::Ocr => {
+                        entry.ocr_bytes += metadata.len();
+                        entry.ocr_result = Some(path.clone());
+                    }
+                    FileKind::Preprocess => entry.preprocess_bytes += metadata.len(),
+                    FileKind::Index => {
+                        if let Some(index) = read_index(&path) {
+                            entry.document = Some(index.document);
+                            entry.page = Some(index.page);
+                        }
+                    }
+                }
+                // The index is written once, so only the data files tell when a page was used
+                if kind != FileKind::Index {
+                    if let Ok(modified) = metadata.modified() {
+                        entry.last_used = entry.last_used.max(modified);
+                    }
+                }
+                entry.files.push(path);
+            }
+        }
+
+        let mut entries: Vec<CacheEntry> = entries.into_values().collect();
+        entries.sort_by_key(|e| e.last_used);
+        Ok(entries)
+    }
+
+    /// Remove every cached page.
+    pub fn clear(&self) -> Result<Removed> {
+        self.remove(self.entries()?)
+    }
+
+    /// Remove pages not used within `older_than`, then the least recently used pages
+    /// until the cache is no larger than `max_bytes`.
+    pub fn prune(&self, older_than: Option<Duration>, max_bytes: Option<u64>) -> Result<Removed> {
+        self.prune_entries(self.entries()?, older_than, max_bytes)
+    }
+
+    fn prune_entries(
+        &self,
+        entries: Vec<CacheEntry>,
+        older_than: Option<Duration>,
+        max_bytes: Option<u64>,
+    ) -> Result<Removed> {
+        let now = SystemTime::now();
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+        let mut doomed = Vec::new();
+        for entry in entries {
+            let expired = older_than
+                .is_some_and(|age| now.duration_since(entry.last_used).unwrap_or_default() > age);
+            let over_limit = max_bytes.is_some_and(|max| total > max);
+            if expired || over_limit || entry.is_orphan() {
+                total -= entry.size;
+                doomed.push(entry);
+            }
+        }
+        self.remove(doomed)
+    }
+
+    /// Keep the cache within `max_size_mb`; called after each document.
+    ///
+    /// The cache is listed once per process and again only when what was written since
+    /// may have taken it over the limit; it is then pruned to 90% of the limit.
+    pub fn enforce_limit(&self) -> Result<Removed> {
+        if !self.config.enabled || self.config.max_size_mb == 0 {
+            return Ok(Removed::default());
+        }
+        let max_bytes = self.config.max_size_mb * 1024 * 1024;
+        let known = KNOWN_SIZES.lock().unwrap().get(&self.config.dir).copied();
+        if known.is_some_and(|size| size <= max_bytes) {
+            return Ok(Removed::default());
+        }
+
+        let entries = self.entries()?;
+        let total: u64 = entries.iter().map(|e| e.size).sum();
+        let removed = if total > max_bytes {
+            let target = max_bytes / 100 * PRUNE_TARGET_PERCENT;
+            self.prune_entries(entries, None, Some(target))?
+        } else {
+            Removed::default()
+        };
+        KNOWN_SIZES
+            .lock()
+            .unwrap()
+            .insert(self.config.dir.clone(), total.saturating_sub(removed.bytes));
+        Ok(removed)
+    }
+
+    fn remove(&self, entries: Vec<CacheEntry>) -> Result<Removed> {
+        let mut removed = Removed::default();
+        for entry in entries {
+            for file in &entry.files {
+                match fs::remove_file(file) {
+                    Ok(()) => {}
+                    // Another run may be cleaning up at the same time
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        return Err(e)
+                            .with_context(|| format!("Failed to remove {}", file.display()))
+                    }
+                }
+            }
+            removed.entries += 1;
+            removed.bytes += entry.size;
+        }
+        if let Some(size) = KNOWN_SIZES.lock().unwrap().get_mut(&self.config.dir) {
+            *size = size.saturating_sub(removed.bytes);
+        }
+        Ok(removed)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Ocr,
+    Preprocess,
+    Index,
+}
+
+fn read_index(path: &Path) -> Option<IndexEntry> {
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// Files directly inside `dir`; none if it does not exist.
+fn files_in(dir: &Path) -> Result<Vec<(PathBuf, fs::Metadata)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read directory {}", dir.display()))
+        }
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            files.push((entry.path(), metadata));
+        }
+    }
+    Ok(files)
+}
+
+/// Mark a cache file as used; the size limit removes the least recently used pages first.
+fn touch(path: &Path) {
+    let _ = fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cache in a fresh temporary directory, removed again on drop.
+    struct TempCache {
+        cache: CacheManager,
+    }
+
+    impl TempCache {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "rust-ocr2md-cache-test-{}-{name}",
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            let config = CacheConfig {
+                enabled: true,
+                dir,
+                preprocess: true,
+                ocr: true,
+                max_size_mb: 0,
+            };
+            Self {
+                cache: CacheManager::new(&config).unwrap(),
+            }
+        }
+
+        /// An OCR result of `size` bytes last used `age_secs` ago.
+        fn add(&self, hash: &str, size: usize, age_secs: u64) {
+            let path = self.cache.ocr_dir.join(format!("{hash}.json"));
+            fs::write(&path, vec![b'x'; size]).unwrap();
+            let used = SystemTime::now() - Duration::from_secs(age_secs);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(used))
+                .unwrap();
+        }
+
+        fn hashes(&self) -> Vec<String> {
+            let entries = self.cache.entries().unwrap();
+            entries.into_iter().map(|e| e.hash).collect()
+        }
+    }
+
+    impl Drop for TempCache {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(self.cache.dir());
+        }
+    }
+
+    #[test]
+    fn prune_by_age() {
+        let temp = TempCache::new("age");
+        temp.add("old", 10, 3 * 24 * 60 * 60);
+        temp.add("new", 10, 60);
+
+        let removed = temp
+            .cache
+            .prune(Some(Duration::from_secs(24 * 60 * 60)), None)
+            .unwrap();
+        assert_eq!(removed.entries, 1);
+        assert_eq!(removed.bytes, 10);
+        assert_eq!(temp.hashes(), ["new"]);
+    }
+
+    #[test]
+    fn prune_to_size_removes_least_recently_used() {
+        let temp = TempCache::new("size");
+        temp.add("a", 100, 300);
+        temp.add("b", 100, 200);
+        temp.add("c", 100, 100);
+
+        let removed = temp.cache.prune(None, Some(250)).unwrap();
+        assert_eq!(removed.entries, 1);
+        assert_eq!(temp.hashes(), ["b", "c"]);
+
+        let removed = temp.cache.prune(None, Some(200)).unwrap();
+        assert_eq!(removed.entries, 0);
+    }
+
+    #[test]
+    fn prune_removes_orphaned_index_files() {
+        let temp = TempCache::new("orphan");
+        temp.add("kept", 10, 60);
+        fs::write(temp.cache.index_dir.join("gone.json"), "{}").unwrap();
+
+        let removed = temp.cache.prune(None, None).unwrap();
+        assert_eq!(removed.entries, 1);
+        assert_eq!(temp.hashes(), ["kept"]);
+    }
+
+    #[test]
+    fn open_creates_nothing() {
+        let temp = TempCache::new("open");
+        let dir = temp.cache.dir().join("nested");
+        let config = CacheConfig {
+            dir: dir.clone(),
+            ..temp.cache.config.clone()
+        };
+        let cache = CacheManager::open(&config);
+        assert!(cache.entries().unwrap().is_empty());
+        assert!(!dir.exists());
+    }
 }
@@ -0,0 +1,321 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::{Args, Subcommand};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rust_ocr2md::cache::{CacheEntry, CacheManager, Removed};
+use rust_ocr2md::config::{self, Config};
+
+use crate::units::format_size;
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Show how large the cache is and what it holds
+    Stats(CacheArgs),
+    /// List the cached documents, or the cached pages of one document
+    List {
+        /// Only this document's pages, with their OCR result files
+        document: Option<PathBuf>,
+
+        #[command(flatten)]
+        args: CacheArgs,
+    },
+    /// Remove everything from the cache
+    Clear(CacheArgs),
+    /// Remove old pages, then the least recently used ones down to a size (default: cache.max_size_mb)
+    Prune {
+        /// Remove pages not used for this long, e.g. "30d", "12h", "2w" (plain number: days)
+        #[arg(long, value_parser = parse_age)]
+        older_than: Option<Duration>,
+
+        /// Shrink the cache to this size, e.g. "500MB", "2GB" (plain number: MB)
+        #[arg(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+
+        #[command(flatten)]
+        args: CacheArgs,
+    },
+}
+
+#[derive(Args)]
+pub struct CacheArgs {
+    /// Config file to use instead of the discovered user/project files
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Named profile from the config files or built in: paper, book, scan-cjk
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Override cache directory
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+}
+
+impl CacheArgs {
+    fn load(&self) -> Result<(Config, CacheManager)> {
+        let mut config = config::load(self.config.as_deref(), self.profile.as_deref())?.config;
+        if let Some(dir) = &self.cache_dir {
+            config.cache.dir = dir.clone();
+        }
+        let cache = CacheManager::open(&config.cache);
+        Ok((config, cache))
+    }
+}
+
+pub fn run(command: &CacheCommand) -> Result<()> {
+    match command {
+        CacheCommand::Stats(args) => stats(args),
+        CacheCommand::List { document, args } => match document {
+            Some(document) => list_pages(args, document),
+            None => list_documents(args),
+        },
+        CacheCommand::Clear(args) => {
+            let (_, cache) = args.load()?;
+            let removed = cache.clear()?;
+            print_removed(&cache, removed);
+            Ok(())
+        }
+        CacheCommand::Prune {
+            older_than,
+            max_size,
+            args,
+        } => {
+            let (config, cache) = args.load()?;
+            // Without options, apply the configured limit
+            let max_size = max_size.or_else(|| {
+                (older_than.is_none() && config.cache.max_size_mb > 0)
+                    .then(|| config.cache.max_size_mb * 1024 * 1024)
+            });
+            let removed = cache.prune(*older_than, max_size)?;
+            print_removed(&cache, removed);
+            Ok(())
+        }
+    }
+}
+
+fn stats(args: &CacheArgs) -> Result<()> {
+    let (config, cache) = args.load()?;
+    let entries: Vec<CacheEntry> = cache
+        .entries()?
+        .into_iter()
+        .filter(|e| e.ocr_result.is_some() || e.preprocess_bytes > 0)
+        .collect();
+    let documents = by_document(&entries);
+    let unknown = entries.iter().filter(|e| e.document.is_none()).count();
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+
+    println!(
+        "📦 Cache: {} ({})",
+        cache.dir().display(),
+        if config.cache.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    println!(
+        "   Pages: {} from {} documents ({} of unknown origin)",
+        entries.len(),
+        documents.keys().filter(|d| d.is_some()).count(),
+        unknown
+    );
+    println!(
+        "   OCR results: {}",
+        format_size(entries.iter().map(|e| e.ocr_bytes).sum())
+    );
+    println!(
+        "   Preprocessed images: {}",
+        format_size(entries.iter().map(|e| e.preprocess_bytes).sum())
+    );
+    match config.cache.max_size_mb {
+        0 => println!("   Total: {} (no size limit)", format_size(total)),
+        limit => println!("   Total: {} of {} MB limit", format_size(total), limit),
+    }
+    if let (Some(oldest), Some(newest)) = (entries.first(), entries.last()) {
+        println!(
+            "   Last used: {} (least recent) to {} (most recent)",
+            format_time(oldest.last_used),
+            format_time(newest.last_used)
+        );
+    }
+    Ok(())
+}
+
+fn list_documents(args: &CacheArgs) -> Result<()> {
+    let (_, cache) = args.load()?;
+    let entries = cache.entries()?;
+    let documents = by_document(&entries);
+    if documents.is_empty() {
+        println!("📦 {} is empty", cache.dir().display());
+        return Ok(());
+    }
+
+    for (document, entries) in &documents {
+        match document {
+            Some(document) => println!("📄 {}", document.display()),
+            None => println!("❔ Unknown document (cached before the index was kept)"),
+        }
+        let mut pages: Vec<usize> = entries.iter().filter_map(|e| e.page).collect();
+        pages.sort_unstable();
+        pages.dedup();
+        let last_used = entries.iter().map(|e| e.last_used).max();
+        println!(
+            "   {} pages, {}, last used {}",
+            if document.is_some() {
+                pages.len()
+            } else {
+                entries.len()
+            },
+            format_size(entries.iter().map(|e| e.size).sum()),
+            last_used.map(format_time).unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+fn list_pages(args: &CacheArgs, document: &Path) -> Result<()> {
+    let (_, cache) = args.load()?;
+    let wanted = std::fs::canonicalize(document).unwrap_or_else(|_| document.to_path_buf());
+    let mut entries: Vec<CacheEntry> = cache
+        .entries()?
+        .into_iter()
+        .filter(|e| {
+            e.document
+                .as_deref()
+                .is_some_and(|d| d == wanted || d.ends_with(document))
+        })
+        .filter(|e| e.ocr_result.is_some() || e.preprocess_bytes > 0)
+        .collect();
+    if entries.is_empty() {
+        println!("Nothing cached for {}", document.display());
+        return Ok(());
+    }
+    entries.sort_by_key(|e| (e.page, e.last_used));
+
+    for entry in &entries {
+        println!(
+            "   page {:>4}  {:>9}  {}  {}",
+            entry.page.unwrap_or_default(),
+            format_size(entry.size),
+            format_time(entry.last_used),
+            entry
+                .ocr_result
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "(preprocessed image only)".to_string())
+        );
+    }
+    // The same page cached at several settings cannot be rendered together
+    let mut pages: Vec<_> = entries.iter().filter_map(|e| e.page).collect();
+    pages.dedup();
+    if pages.len() < entries.len() {
+        println!("\nSome pages are cached for several settings (DPI, engine, languages).");
+    }
+    println!("\nOCR result files can be rebuilt without OCR: rust-ocr2md render <FILES> -o out.md");
+    Ok(())
+}
+
+/// Entries with data grouped by document; unknown documents come first.
+fn by_document(entries: &[CacheEntry]) -> BTreeMap<Option<PathBuf>, Vec<&CacheEntry>> {
+    let mut documents: BTreeMap<Option<PathBuf>, Vec<&CacheEntry>> = BTreeMap::new();
+    for entry in entries {
+        if entry.ocr_result.is_some() || entry.preprocess_bytes > 0 {
+            documents
+                .entry(entry.document.clone())
+                .or_default()
+                .push(entry);
+        }
+    }
+    documents
+}
+
+fn print_removed(cache: &CacheManager, removed: Removed) {
+    println!(
+        "🧹 Removed {} cached pages ({}) from {}",
+        removed.entries,
+        format_size(removed.bytes),
+        cache.dir().display()
+    );
+}
+
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// "30d", "12h", "90m", "2w"; a plain number is days.
+fn parse_age(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid age \"{}\", expected e.g. 30d", value))?;
+    let seconds = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        other => return Err(format!("unknown unit \"{}\" (use s, m, h, d or w)", other)),
+    };
+    Ok(Duration::from_secs(number * seconds))
+}
+
+/// "500MB", "2GB", "1.5G", "800k"; a plain number is MB.
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size \"{}\", expected e.g. 500MB", value))?;
+    let factor: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "" | "m" | "mb" | "mib" => 1024 * 1024,
+        "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+        other => return Err(format!("unknown unit \"{}\" (use KB, MB or GB)", other)),
+    };
+    Ok((number * factor as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ages() {
+        assert_eq!(parse_age("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_age("15m"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_age("12h"), Ok(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(parse_age("30d"), Ok(Duration::from_secs(30 * 24 * 60 * 60)));
+        assert_eq!(parse_age("30"), Ok(Duration::from_secs(30 * 24 * 60 * 60)));
+        assert_eq!(
+            parse_age(" 2w "),
+            Ok(Duration::from_secs(14 * 24 * 60 * 60))
+        );
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3y").is_err());
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("500"), Ok(500 * 1024 * 1024));
+        assert_eq!(parse_size("500MB"), Ok(500 * 1024 * 1024));
+        assert_eq!(parse_size("2GB"), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("1.5g"), Ok(3 * 512 * 1024 * 1024));
+        assert_eq!(parse_size("800k"), Ok(800 * 1024));
+        assert_eq!(parse_size("64 b"), Ok(64));
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("5TB").is_err());
+    }
+}
@@ -1,15 +1,59 @@
-use clap::ValueEnum;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq)]
+/// Project config file, looked up in the current directory and its parents.
+pub const PROJECT_CONFIG_FILE: &str = "rust-ocr2md.toml";
+
+/// Profiles shipped with the tool; config files may override or add to them.
+const BUILTIN_PROFILES: &str = r#"
+[paper]
+dpi = 300
+languages = "eng+equ"
+layout = true
+math_ocr = true
+figures = true
+outline = true
+
+[book]
+dpi = 300
+math_ocr = false
+outline = true
+toc = true
+
+[scan-cjk]
+dpi = 400
+engine = "paddle"
+languages = "chi_sim+chi_tra+jpn+kor+eng"
+detect_language = true
+preprocess = true
+math_ocr = false
+"#;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 #[serde(rename_all = "lowercase")]
 pub enum EngineKind {
     Tesseract,
     Paddle,
 }
 
+/// What to do when a page fails.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorPolicy {
+    /// Leave the page out of the Markdown
+    Skip,
+    /// Leave an HTML comment naming the page and the error in its place
+    Placeholder,
+    /// Stop converting the document at the first failed page
+    Abort,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct CacheConfig {
     /// Toggle on/off disk cache
     pub enabled: bool,
@@ -19,9 +63,12 @@ pub struct CacheConfig {
     pub preprocess: bool,
     /// Cache OCR results to skip re-run
     pub ocr: bool,
+    /// Size limit in MB; the least recently used pages are removed beyond it (0 = no limit)
+    pub max_size_mb: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     /// DPI for rendering PDF pages
     pub dpi: u32,
@@ -49,6 +96,34 @@ pub struct Config {
     pub use_gpu: bool,
     /// Auto-tune config based on document heuristics
     pub auto_config: bool,
+    /// Extract figures as image assets
+    pub figures: bool,
+    /// Directory where figure assets are written
+    pub assets_dir: PathBuf,
+    /// Use PDF bookmarks as Markdown headings
+    pub outline: bool,
+    /// Prepend a linked table of contents built from the outline
+    pub toc: bool,
+    /// Carry link, highlight and comment annotations into the output
+    pub annotations: bool,
+    /// Start the Markdown with a title and a metadata list
+    pub metadata: bool,
+    /// Join the lines of a text block into one line instead of keeping OCR line breaks
+    pub join_lines: bool,
+    /// Rejoin words hyphenated across a line break
+    pub dehyphenate: bool,
+    /// Join consecutive text blocks that continue the same sentence into one paragraph
+    pub merge_paragraphs: bool,
+    /// How failed pages are handled
+    pub on_error: ErrorPolicy,
+    /// Largest page image in pixels; larger pages are rendered at a lower DPI (0 = no limit)
+    pub max_page_pixels: u64,
+    /// Seconds a page may take before it is abandoned (0 = no limit)
+    pub page_timeout: u64,
+    /// Most pages converted from one document (0 = no limit)
+    pub max_pages: usize,
+    /// Directory for per-page block overlays and formula crops, for diagnosing bad output
+    pub debug_dir: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -69,9 +144,291 @@ impl Default for Config {
                 dir: PathBuf::from(".cache/rust-ocr2md"),
                 preprocess: true,
                 ocr: true,
+                max_size_mb: 2048,
             },
             use_gpu: false,
             auto_config: true,
+            figures: true,
+            assets_dir: PathBuf::from("assets"),
+            outline: true,
+            toc: false,
+            annotations: true,
+            metadata: true,
+            join_lines: false,
+            dehyphenate: false,
+            merge_paragraphs: false,
+            on_error: ErrorPolicy::Placeholder,
+            max_page_pixels: 50_000_000,
+            page_timeout: 300,
+            max_pages: 10_000,
+            debug_dir: None,
         }
     }
 }
+
+/// The merged configuration and where it came from.
+pub struct LoadedConfig {
+    pub config: Config,
+    /// Config files applied, lowest precedence first
+    pub files: Vec<PathBuf>,
+    /// Profile applied on top of the files, if any
+    pub profile: Option<String>,
+    /// Dotted keys (e.g. "dpi", "cache.dir") set by a file, the profile or a flag,
+    /// which auto-config leaves alone
+    pub explicit: BTreeSet<String>,
+}
+
+impl LoadedConfig {
+    /// Layer `overrides`, shaped like a config file, on top and mark its keys explicit.
+    pub fn apply_overrides(&mut self, overrides: toml::Table) -> Result<()> {
+        let overrides = toml::Value::Table(overrides);
+        collect_keys("", &overrides, &mut self.explicit);
+        let mut merged = toml::Value::try_from(&self.config)?;
+        merge_value(&mut merged, overrides);
+        self.config = merged
+            .try_into()
+            .context("Invalid configuration override")?;
+        Ok(())
+    }
+}
+
+/// Build the configuration from defaults, config files and a named profile.
+///
+/// Without `config_path`, the user-level file and then the nearest project file are merged
+/// in that order. The profile comes from `profile` or the files' `profile` key and is
+/// applied last; command-line flags are layered on top by the caller.
+pub fn load(config_path: Option<&Path>, profile: Option<&str>) -> Result<LoadedConfig> {
+    let mut merged = toml::Value::try_from(Config::default())?;
+    let mut profiles: toml::Table =
+        toml::from_str(BUILTIN_PROFILES).context("Invalid built-in profiles")?;
+    let mut file_profile = None;
+    let mut explicit = BTreeSet::new();
+
+    let files = match config_path {
+        Some(path) => {
+            if !path.is_file() {
+                anyhow::bail!("Config file not found: {}", path.display());
+            }
+            vec![path.to_path_buf()]
+        }
+        None => [user_config_path(), project_config_path()]
+            .into_iter()
+            .flatten()
+            .filter(|path| path.is_file())
+            .collect(),
+    };
+
+    for path in &files {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut table: toml::Table = toml::from_str(&contents)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+        if let Some(value) = table.remove("profile") {
+            let name = value
+                .as_str()
+                .with_context(|| format!("`profile` must be a string in {}", path.display()))?;
+            file_profile = Some(name.to_string());
+        }
+        if let Some(value) = table.remove("profiles") {
+            let toml::Value::Table(file_profiles) = value else {
+                anyhow::bail!("`profiles` must be a table in {}", path.display());
+            };
+            for (name, values) in file_profiles {
+                merge_value(
+                    profiles
+                        .entry(name)
+                        .or_insert(toml::Value::Table(toml::Table::new())),
+                    values,
+                );
+            }
+        }
+        let table = toml::Value::Table(table);
+        collect_keys("", &table, &mut explicit);
+        merge_value(&mut merged, table);
+    }
+
+    let profile = profile.map(str::to_string).or(file_profile);
+    if let Some(name) = &profile {
+        let values = profiles.get(name).cloned().with_context(|| {
+            format!(
+                "Unknown profile \"{}\" (available: {})",
+                name,
+                profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        collect_keys("", &values, &mut explicit);
+        merge_value(&mut merged, values);
+    }
+
+    let config: Config = merged.try_into().with_context(|| match files.last() {
+        Some(path) => format!("Invalid configuration in {}", path.display()),
+        None => "Invalid configuration".to_string(),
+    })?;
+
+    Ok(LoadedConfig {
+        config,
+        files,
+        profile,
+        explicit,
+    })
+}
+
+fn collect_keys(prefix: &str, value: &toml::Value, keys: &mut BTreeSet<String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                collect_keys(&key, value, keys);
+            }
+        }
+        _ => {
+            keys.insert(prefix.to_string());
+        }
+    }
+}
+
+/// Recursively merge tables; any other value in `overlay` replaces the one in `base`.
+fn merge_value(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_value(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// `rust-ocr2md/config.toml` under the platform's user config directory.
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    base.map(|dir| dir.join("rust-ocr2md").join("config.toml"))
+}
+
+/// The nearest `rust-ocr2md.toml` in the current directory or one of its parents.
+pub fn project_config_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Load `contents` as the only config file.
+    fn load_str(name: &str, contents: &str, profile: Option<&str>) -> Result<LoadedConfig> {
+        let path = std::env::temp_dir().join(format!(
+            "rust-ocr2md-config-test-{}-{name}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        let loaded = load(Some(&path), profile);
+        let _ = std::fs::remove_file(&path);
+        loaded
+    }
+
+    fn keys(loaded: &LoadedConfig) -> Vec<&str> {
+        loaded.explicit.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn file_values_override_defaults() {
+        let loaded = load_str("file", "dpi = 200\n[cache]\ndir = \"cache\"\n", None).unwrap();
+        assert_eq!(loaded.config.dpi, 200);
+        assert_eq!(loaded.config.cache.dir, PathBuf::from("cache"));
+        assert_eq!(loaded.config.languages, Config::default().languages);
+        assert_eq!(loaded.profile, None);
+        assert_eq!(keys(&loaded), vec!["cache.dir", "dpi"]);
+    }
+
+    #[test]
+    fn profile_is_applied_over_the_file() {
+        let loaded = load_str("profile", "dpi = 200\nthreads = 2\n", Some("paper")).unwrap();
+        assert_eq!(loaded.config.dpi, 300);
+        assert_eq!(loaded.config.languages, "eng+equ");
+        assert_eq!(loaded.config.threads, 2);
+        assert_eq!(loaded.profile.as_deref(), Some("paper"));
+        assert!(loaded.explicit.contains("languages"));
+        assert!(loaded.explicit.contains("threads"));
+        assert!(!loaded.explicit.contains("engine"));
+    }
+
+    #[test]
+    fn files_can_select_and_extend_profiles() {
+        let contents = "profile = \"paper\"\n[profiles.paper]\ndpi = 150\n";
+        let loaded = load_str("file-profile", contents, None).unwrap();
+        assert_eq!(loaded.profile.as_deref(), Some("paper"));
+        assert_eq!(loaded.config.dpi, 150);
+        // Keys the file did not override keep the built-in profile's values
+        assert_eq!(loaded.config.languages, "eng+equ");
+
+        let loaded = load_str("flag-profile", contents, Some("book")).unwrap();
+        assert_eq!(loaded.profile.as_deref(), Some("book"));
+        assert_eq!(loaded.config.dpi, 300);
+        assert!(loaded.config.toc);
+    }
+
+    #[test]
+    fn rejects_unknown_profiles() {
+        let error = load_str("unknown-profile", "", Some("poster"))
+            .err()
+            .unwrap();
+        let message = error.to_string();
+        assert!(message.contains("Unknown profile \"poster\""), "{message}");
+        assert!(message.contains("paper"), "{message}");
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(load_str("unknown-key", "dpii = 300\n", None).is_err());
+        assert!(load_str("unknown-nested", "[cache]\nsize = 1\n", None).is_err());
+        assert!(load_str(
+            "unknown-profile-key",
+            "[profiles.paper]\ndpii = 1\n",
+            Some("paper")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn overrides_are_explicit() {
+        let mut loaded = load_str("overrides", "", None).unwrap();
+        assert!(loaded.explicit.is_empty());
+
+        let overrides: toml::Table =
+            toml::from_str("dpi = 600\n[cache]\nenabled = false\n").unwrap();
+        loaded.apply_overrides(overrides).unwrap();
+        assert_eq!(loaded.config.dpi, 600);
+        assert!(!loaded.config.cache.enabled);
+        assert!(loaded.config.cache.ocr);
+        assert_eq!(keys(&loaded), vec!["cache.enabled", "dpi"]);
+
+        let invalid: toml::Table = toml::from_str("dpi = \"high\"\n").unwrap();
+        assert!(loaded.apply_overrides(invalid).is_err());
+    }
+
+    #[test]
+    fn reports_missing_config_files() {
+        let path = std::env::temp_dir().join("rust-ocr2md-config-test-missing.toml");
+        let error = load(Some(&path), None).err().unwrap();
+        assert!(error.to_string().contains("Config file not found"));
+    }
+}
@@ -0,0 +1,86 @@
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+use rust_ocr2md::progress::{Event, ProgressHandler};
+use std::io::Write;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// Human-readable progress bar
+    Bar,
+    /// One JSON event per line on stderr
+    Json,
+}
+
+/// Converter progress on stderr, shown either as a bar per document or as JSON-lines events.
+pub struct Console {
+    mode: ProgressMode,
+    bar: Mutex<Option<ProgressBar>>,
+}
+
+impl Console {
+    pub fn new(mode: ProgressMode) -> Self {
+        Self {
+            mode,
+            bar: Mutex::new(None),
+        }
+    }
+
+    fn new_bar(pages: usize) -> ProgressBar {
+        let bar = ProgressBar::new(pages as u64);
+        if let Ok(style) = ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
+        {
+            bar.set_style(style.progress_chars("█▓▒░ "));
+        }
+        bar
+    }
+
+    /// Print a line without tearing the bar, if one is running.
+    fn println(&self, message: &str) {
+        match self.bar.lock().unwrap().as_ref() {
+            Some(bar) if !bar.is_finished() => bar.suspend(|| eprintln!("{}", message)),
+            _ => eprintln!("{}", message),
+        }
+    }
+
+    fn finish(&self, message: &'static str) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            if !bar.is_finished() {
+                bar.finish_with_message(message);
+            }
+        }
+    }
+}
+
+impl ProgressHandler for Console {
+    fn on_event(&self, event: &Event) {
+        if self.mode == ProgressMode::Json {
+            emit(event);
+            return;
+        }
+        match event {
+            Event::Info { message } => self.println(message),
+            Event::DocumentStarted { pages, resumed, .. } => {
+                *self.bar.lock().unwrap() = Some(Self::new_bar(pages.saturating_sub(*resumed)));
+            }
+            Event::PageFinished { .. } | Event::PageError { .. } => {
+                if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+                    bar.inc(1);
+                }
+            }
+            Event::Interrupted { .. } => self.finish("⏸️  Interrupted"),
+            Event::DocumentFinished { .. } => self.finish("✅ OCR completed!"),
+            _ => {}
+        }
+    }
+}
+
+/// Write an event to stderr regardless of any progress bar.
+pub fn emit(event: &Event) {
+    if let Ok(line) = serde_json::to_string(event) {
+        let mut stderr = std::io::stderr().lock();
+        let _ = writeln!(stderr, "{}", line);
+        let _ = stderr.flush();
+    }
+}
@@ -0,0 +1,467 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::analysis;
+use crate::config::{Config, EngineKind, ErrorPolicy};
+use crate::error::{OcrError, PageFailure};
+use crate::manifest::Manifest;
+use crate::markdown_builder::{MarkdownBuilder, StreamEnd};
+use crate::ocr_engine::OcrEngine;
+use crate::page_range::parse_page_range;
+use crate::page_source::{self, PageOutcome, PageSource};
+use crate::pdf_processor::PageResult;
+use crate::progress::{self, Event, NoProgress, ProgressHandler};
+use crate::report::DocumentReport;
+use crate::saved::SavedDocument;
+
+/// Converts documents to Markdown with one configuration.
+///
+/// OCR models are loaded the first time a document needs them and reused for every later
+/// document, so keep one converter around for many conversions.
+///
+/// ```no_run
+/// use rust_ocr2md::{Config, Converter};
+///
+/// let converter = Converter::new(Config::default());
+/// let document = converter.convert_file("paper.pdf")?;
+/// println!("{}", document.markdown);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct Converter {
+    config: Config,
+    explicit: BTreeSet<String>,
+    password: Option<String>,
+    progress: Box<dyn ProgressHandler>,
+    cancel: Arc<AtomicBool>,
+    /// OCR engines by backend, as auto-config may pick a different one per document
+    engines: Mutex<Vec<(EngineKind, OcrEngine)>>,
+}
+
+/// Where and how one document is converted.
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    /// Pages to convert, e.g. "1-10,15" (see `--pages`); all pages when unset
+    pub pages: Option<String>,
+    /// Also write the Markdown to this file, streaming it page by page
+    pub output: Option<PathBuf>,
+    /// Stream the Markdown to stdout instead of writing `output`, which then only places
+    /// the figure assets and names the report
+    pub stdout: bool,
+    /// Directory for extracted figures; by default `config.assets_dir` below the output's
+    /// directory
+    pub assets_dir: Option<PathBuf>,
+    /// Carry over the pages an earlier, unfinished run recorded for `output`
+    pub resume: bool,
+    /// Also save the OCR results to this JSON file, to build the Markdown again later
+    /// with other options (see [`SavedDocument`])
+    pub json: Option<PathBuf>,
+}
+
+/// The result of converting one document.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub input: PathBuf,
+    pub markdown: String,
+    /// Pages that converted, in page order
+    pub pages: Vec<PageResult>,
+    /// Pages that failed, in page order
+    pub failures: Vec<PageFailure>,
+    /// Pages never started because the conversion was cancelled
+    pub interrupted: Vec<usize>,
+    /// Number of pages selected for conversion
+    pub selected: usize,
+    /// Configuration the document was converted with, after auto-config
+    pub config: Config,
+    /// Per-page timings, confidence, warnings and errors
+    pub report: DocumentReport,
+}
+
+impl Document {
+    /// Every selected page converted.
+    pub fn is_complete(&self) -> bool {
+        self.pages.len() == self.selected
+    }
+}
+
+impl Converter {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            explicit: BTreeSet::new(),
+            password: None,
+            progress: Box::new(NoProgress),
+            cancel: Arc::default(),
+            engines: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Config keys set explicitly by the user; auto-config reports but never changes them.
+    pub fn with_explicit_keys(mut self, keys: BTreeSet<String>) -> Self {
+        self.explicit = keys;
+        self
+    }
+
+    /// Password for encrypted PDFs.
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.password = password;
+        self
+    }
+
+    pub fn with_progress(mut self, handler: impl ProgressHandler + 'static) -> Self {
+        self.progress = Box::new(handler);
+        self
+    }
+
+    /// Once `flag` is set, the page being converted is finished and the rest are skipped.
+    pub fn with_cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = flag;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Load the OCR engine for the configured backend now instead of on first use.
+    pub fn load_engine(&self) -> Result<()> {
+        self.engine(&self.config).map(|_| ())
+    }
+
+    /// Convert a PDF, image, multi-page TIFF or directory of images.
+    pub fn convert_file(&self, path: impl AsRef<Path>) -> Result<Document> {
+        self.convert(path.as_ref(), &ConvertOptions::default())
+    }
+
+    /// Convert a PDF held in memory.
+    pub fn convert_bytes(&self, bytes: Vec<u8>) -> Result<Document> {
+        self.convert_bytes_with(bytes, Path::new("document.pdf"), &ConvertOptions::default())
+    }
+
+    /// [`convert_file`](Self::convert_file) with explicit options.
+    pub fn convert(&self, path: &Path, options: &ConvertOptions) -> Result<Document> {
+        let source = page_source::open(path, self.password.clone())?;
+        self.convert_source(Arc::from(source), options)
+    }
+
+    /// [`convert_bytes`](Self::convert_bytes) with explicit options; `name` is used for
+    /// display and cache keys.
+    pub fn convert_bytes_with(
+        &self,
+        bytes: Vec<u8>,
+        name: &Path,
+        options: &ConvertOptions,
+    ) -> Result<Document> {
+        let source = page_source::from_bytes(bytes, name, self.password.clone())?;
+        self.convert_source(Arc::from(source), options)
+    }
+
+    fn info(&self, message: String) {
+        self.progress.on_event(&Event::Info { message });
+    }
+
+    /// The engine for `config.engine`, created the first time a document needs it.
+    fn engine(&self, config: &Config) -> Result<OcrEngine> {
+        let mut engines = self.engines.lock().unwrap();
+        if let Some(idx) = engines.iter().position(|(kind, _)| *kind == config.engine) {
+            if !engines[idx].1.is_stuck() {
+                return Ok(engines[idx].1.clone());
+            }
+            // An earlier document gave up on a page that still holds it
+            self.info("♻️  Reloading the OCR engine after a timed-out page".to_string());
+            engines.remove(idx);
+        }
+        let engine = OcrEngine::new(config)?;
+        engines.push((config.engine, engine.clone()));
+        Ok(engine)
+    }
+
+    /// When `auto_config` is on, tune a copy of the configuration to the document.
+    fn document_config(&self, source: &dyn PageSource) -> Config {
+        let mut config = self.config.clone();
+        if !config.auto_config {
+            return config;
+        }
+
+        match analysis::analyze(source, &config) {
+            Ok(analysis) => {
+                self.info(format!(
+                    "🔍 Auto-config ({} sampled pages): {}",
+                    analysis.sampled.len(),
+                    analysis.summary()
+                ));
+                for change in analysis.tune(&mut config, &self.explicit) {
+                    if change.applied {
+                        self.info(format!(
+                            "   {}: {} → {} ({})",
+                            change.key, change.from, change.to, change.reason
+                        ));
+                    } else {
+                        self.info(format!(
+                            "   {}: kept {} as set explicitly (suggested {} for {})",
+                            change.key, change.from, change.to, change.reason
+                        ));
+                    }
+                }
+            }
+            Err(e) => self.info(format!("⚠️  Auto-config skipped: {}", e)),
+        }
+        config
+    }
+
+    fn convert_source(
+        &self,
+        source: Arc<dyn PageSource>,
+        options: &ConvertOptions,
+    ) -> Result<Document> {
+        let start = Instant::now();
+        let input_path = source.path();
+        let output_path = options.output.as_deref();
+        let to_stdout = options.stdout;
+
+        // A configured assets_dir is relative to the output file
+        let mut config = self.document_config(source.as_ref());
+        let output_dir = output_path
+            .and_then(Path::parent)
+            .unwrap_or_else(|| Path::new(""));
+        config.assets_dir = match &options.assets_dir {
+            Some(dir) => dir.clone(),
+            None => output_dir.join(&config.assets_dir),
+        };
+        // One subdirectory per document so a batch does not overwrite its own pages
+        if let Some(dir) = &config.debug_dir {
+            let stem = input_path.file_stem().unwrap_or(input_path.as_os_str());
+            config.debug_dir = Some(dir.join(stem));
+        }
+        self.info(format!(
+            "⚙️  Config: {} DPI, {} threads, Languages: {}, Engine: {:?}",
+            config.dpi, config.threads, config.languages, config.engine
+        ));
+        let ocr_engine = self.engine(&config)?;
+
+        let total_pages = source.page_count();
+        self.info(format!("📖 Total pages: {}", total_pages));
+        if config.outline && !source.outline().is_empty() {
+            self.info(format!("🔖 Outline entries: {}", source.outline().len()));
+        }
+
+        // Parse page range
+        let pages = options.pages.as_deref().unwrap_or("all");
+        let page_range = parse_page_range(pages, total_pages, source.page_labels())?;
+        self.info(format!("🎯 Processing pages: {:?}", page_range));
+
+        // Pages finished by an earlier, interrupted run are taken from its manifest
+        if options.resume && (output_path.is_none() || to_stdout) {
+            anyhow::bail!(
+                "resuming needs an output file; it cannot be used with stdout output or in memory"
+            );
+        }
+        let fingerprint = format!(
+            "{}|dpi:{}|max_pixels:{}",
+            page_source::config_fingerprint(&config),
+            config.dpi,
+            config.max_page_pixels
+        );
+        let (mut manifest, resumed) = match output_path.filter(|_| !to_stdout) {
+            Some(output_path) => {
+                let (manifest, resumed) = Manifest::start(
+                    output_path,
+                    input_path,
+                    &fingerprint,
+                    options.resume,
+                    self.progress.as_ref(),
+                )?;
+                (Some(manifest), resumed)
+            }
+            None => (None, Vec::new()),
+        };
+        let mut results: Vec<_> = resumed
+            .into_iter()
+            .filter(|r| page_range.contains(&r.page_num))
+            .collect();
+        let todo: Vec<usize> = page_range
+            .iter()
+            .copied()
+            .filter(|page| !results.iter().any(|r| r.page_num == *page))
+            .collect();
+        if !results.is_empty() {
+            self.info(format!(
+                "♻️  Resuming: {} of {} pages already converted",
+                results.len(),
+                page_range.len()
+            ));
+        }
+
+        let outline = if config.outline {
+            source.outline().to_vec()
+        } else {
+            Vec::new()
+        };
+        let new_builder = || {
+            MarkdownBuilder::from_config(&config)
+                .with_assets_link(assets_link(output_dir, &config.assets_dir))
+                .with_outline(outline.clone())
+                .with_document_info(source.info())
+                .with_page_labels(source.page_labels().to_vec())
+        };
+
+        // Sections are written as soon as every earlier page is done
+        let stream_builder = new_builder();
+        let writer: Option<Box<dyn Write>> = match output_path {
+            _ if to_stdout => Some(Box::new(io::stdout())),
+            Some(output_path) => {
+                let file = std::fs::File::create(output_path)
+                    .with_context(|| format!("Failed to create {}", output_path.display()))?;
+                Some(Box::new(io::BufWriter::new(file)))
+            }
+            None => None,
+        };
+        let mut stream = match writer {
+            Some(writer) => {
+                let mut stream = stream_builder.stream(
+                    writer,
+                    input_path,
+                    &page_range,
+                    config.on_error == ErrorPolicy::Placeholder,
+                )?;
+                for result in &results {
+                    stream.page(result)?;
+                }
+                Some(stream)
+            }
+            None => None,
+        };
+
+        self.progress.on_event(&Event::DocumentStarted {
+            input: input_path.display().to_string(),
+            pages: page_range.len(),
+            resumed: results.len(),
+        });
+
+        // Process pages with OCR
+        let processed = page_source::process_pages(
+            &source,
+            &todo,
+            &ocr_engine,
+            &config,
+            self.progress.as_ref(),
+            &self.cancel,
+            &mut |outcome| {
+                match outcome {
+                    PageOutcome::Done(result) => {
+                        if let Some(manifest) = manifest.as_mut() {
+                            manifest.record(result)?;
+                        }
+                        if let Some(stream) = stream.as_mut() {
+                            stream.page(result)?;
+                        }
+                    }
+                    PageOutcome::Failed(failure) => {
+                        if let Some(stream) = stream.as_mut() {
+                            stream.failed(failure)?;
+                        }
+                    }
+                }
+                Ok(())
+            },
+        );
+        // `--on-error abort` ends here too: the streamed output says where and why it stopped
+        // instead of ending mid-document
+        let processed = match processed {
+            Ok(processed) => processed,
+            Err(e) => {
+                if let Some(stream) = stream {
+                    let end = match e.downcast_ref::<OcrError>() {
+                        Some(OcrError::PageFailed(failure)) => StreamEnd::Aborted(failure),
+                        _ => StreamEnd::Failed(&e),
+                    };
+                    let _ = stream.finish(end);
+                }
+                return Err(e);
+            }
+        };
+        if let Some(stream) = stream {
+            stream.finish(StreamEnd::Completed)?;
+        }
+        results.extend(processed.results);
+        results.sort_by_key(|r| r.page_num);
+        let complete = processed.failures.is_empty() && processed.interrupted.is_empty();
+
+        // Built whole, with anchors and the header exact now that every page is known;
+        // an output file is rewritten with it
+        let markdown = new_builder()
+            .with_failed_pages(match config.on_error {
+                ErrorPolicy::Placeholder => processed.failures.clone(),
+                ErrorPolicy::Skip | ErrorPolicy::Abort => Vec::new(),
+            })
+            .with_missing_pages(processed.interrupted.clone())
+            .build(&results, input_path)?;
+        if let Some(output_path) = output_path.filter(|_| !to_stdout) {
+            self.info("📝 Finalizing Markdown...".to_string());
+            std::fs::write(output_path, &markdown)?;
+        }
+        if let Some(json_path) = &options.json {
+            SavedDocument {
+                info: source.info(),
+                // Kept even when unused here, so `render` can turn it on
+                outline: source.outline().to_vec(),
+                page_labels: source.page_labels().to_vec(),
+                failures: processed.failures.clone(),
+                ..SavedDocument::new(input_path, results.clone())
+            }
+            .save(json_path)?;
+            self.info(format!("💾 OCR results saved to {}", json_path.display()));
+        }
+        match manifest {
+            Some(manifest) if complete => manifest.finish()?,
+            Some(manifest) => self.info(format!(
+                "📒 Progress saved to {}; run again with --resume to convert the remaining pages",
+                manifest.path().display()
+            )),
+            None => {}
+        }
+
+        self.progress.on_event(&Event::DocumentFinished {
+            input: input_path.display().to_string(),
+            output: output_path
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            pages: page_range.len(),
+            converted: results.len(),
+            elapsed_ms: progress::millis(start.elapsed()),
+        });
+
+        Ok(Document {
+            input: input_path.to_path_buf(),
+            markdown,
+            report: DocumentReport::new(
+                input_path,
+                output_path,
+                processed.reports,
+                start.elapsed(),
+            ),
+            pages: results,
+            failures: processed.failures,
+            interrupted: processed.interrupted,
+            selected: page_range.len(),
+            config,
+        })
+    }
+}
+
+/// Link prefix for figure assets as seen from the Markdown file's directory.
+fn assets_link(output_dir: &Path, assets_dir: &Path) -> String {
+    let relative = assets_dir.strip_prefix(output_dir).unwrap_or(assets_dir);
+    if relative.is_absolute() {
+        return relative.display().to_string();
+    }
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
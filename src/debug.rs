@@ -0,0 +1,139 @@
+use crate::ocr_engine::{BlockType, BoundingBox, OcrBlock};
+use anyhow::{Context, Result};
+use image::{DynamicImage, Rgb, RgbImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut};
+use imageproc::rect::Rect;
+use std::path::Path;
+
+/// 3×5 bitmaps for the label characters, one row per byte, most significant bit left.
+const GLYPHS: [(char, [u8; 5]); 11] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+];
+
+fn block_color(block_type: &BlockType) -> Rgb<u8> {
+    match block_type {
+        BlockType::Text => Rgb([30, 100, 230]),
+        BlockType::Formula => Rgb([220, 40, 40]),
+        BlockType::Table => Rgb([30, 160, 60]),
+        BlockType::Figure => Rgb([230, 130, 0]),
+        BlockType::Unknown => Rgb([128, 128, 128]),
+    }
+}
+
+/// Write the debug output for one page into `dir`.
+///
+/// `page-NNNN.png` is `image` with every block's box drawn in its type's color and
+/// labelled with its 1-based reading-order index and confidence; `page-NNNN.json` lists
+/// the same blocks in the same order. Each formula crop is written as
+/// `page-NNNN-formula-K.png`, K being the index of the block it was recognized for.
+pub fn write_page(
+    dir: &Path,
+    page_num: usize,
+    image: &DynamicImage,
+    blocks: &[OcrBlock],
+    formula_crops: &[(BoundingBox, DynamicImage)],
+) -> Result<()> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create debug directory {}", dir.display()))?;
+
+    let overlay = draw_overlay(image, blocks);
+    let path = dir.join(format!("page-{:04}.png", page_num));
+    overlay
+        .save(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let path = dir.join(format!("page-{:04}.json", page_num));
+    std::fs::write(&path, serde_json::to_string_pretty(blocks)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    for (n, (bbox, crop)) in formula_crops.iter().enumerate() {
+        let index = blocks
+            .iter()
+            .position(|b| b.bbox.as_ref().is_some_and(|b| same_box(b, bbox)));
+        let name = match index {
+            Some(idx) => format!("page-{:04}-formula-{}.png", page_num, idx + 1),
+            // The block was inside a detected figure and replaced by it
+            None => format!("page-{:04}-formula-dropped-{}.png", page_num, n + 1),
+        };
+        let path = dir.join(name);
+        crop.save(&path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn draw_overlay(image: &DynamicImage, blocks: &[OcrBlock]) -> RgbImage {
+    let mut canvas = image.to_rgb8();
+    // Thick enough to see on a page scaled down to fit the screen
+    let thickness = (canvas.width().max(canvas.height()) / 1000).max(2);
+
+    for (idx, block) in blocks.iter().enumerate() {
+        let Some(bbox) = &block.bbox else {
+            continue;
+        };
+        let color = block_color(&block.block_type);
+        for t in 0..thickness {
+            if bbox.width <= 2 * t || bbox.height <= 2 * t {
+                break;
+            }
+            let rect = Rect::at((bbox.x + t) as i32, (bbox.y + t) as i32)
+                .of_size(bbox.width - 2 * t, bbox.height - 2 * t);
+            draw_hollow_rect_mut(&mut canvas, rect, color);
+        }
+
+        let label = format!("{} {:.2}", idx + 1, block.confidence);
+        let label_height = 7 * thickness;
+        // Above the box, or just inside it at the top of the page
+        let y = if bbox.y >= label_height {
+            bbox.y - label_height
+        } else {
+            bbox.y
+        };
+        draw_label(&mut canvas, bbox.x, y, &label, thickness, color);
+    }
+    canvas
+}
+
+/// White text on a box of `background`, each glyph pixel drawn as a `scale`-sized square.
+fn draw_label(canvas: &mut RgbImage, x: u32, y: u32, text: &str, scale: u32, background: Rgb<u8>) {
+    let advance = 4 * scale;
+    let width = text.chars().count() as u32 * advance + scale;
+    draw_filled_rect_mut(
+        canvas,
+        Rect::at(x as i32, y as i32).of_size(width, 7 * scale),
+        background,
+    );
+
+    for (n, ch) in text.chars().enumerate() {
+        let Some((_, rows)) = GLYPHS.iter().find(|(c, _)| *c == ch) else {
+            continue;
+        };
+        let left = x + scale + n as u32 * advance;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) != 0 {
+                    let rect = Rect::at(
+                        (left + col * scale) as i32,
+                        (y + scale + row as u32 * scale) as i32,
+                    )
+                    .of_size(scale, scale);
+                    draw_filled_rect_mut(canvas, rect, Rgb([255, 255, 255]));
+                }
+            }
+        }
+    }
+}
+
+fn same_box(a: &BoundingBox, b: &BoundingBox) -> bool {
+    (a.x, a.y, a.width, a.height) == (b.x, b.y, b.width, b.height)
+}
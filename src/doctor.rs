@@ -0,0 +1,333 @@
+use anyhow::Result;
+use pdfium_render::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use rust_ocr2md::config::{Config, EngineKind};
+use rust_ocr2md::ocr_engine::{self, OcrEngine, PADDLE_MODEL_FILES};
+
+use crate::units::format_size;
+use crate::PipelineArgs;
+
+/// Collected findings; problems fail the run, warnings only inform.
+#[derive(Default)]
+struct Report {
+    problems: usize,
+    fixes: Vec<String>,
+}
+
+impl Report {
+    fn section(&self, title: &str) {
+        println!("\n{}", title);
+    }
+
+    fn ok(&self, message: impl AsRef<str>) {
+        println!("  ✅ {}", message.as_ref());
+    }
+
+    fn warn(&mut self, message: impl AsRef<str>, fix: impl Into<String>) {
+        println!("  ⚠️  {}", message.as_ref());
+        self.fixes.push(fix.into());
+    }
+
+    fn fail(&mut self, message: impl AsRef<str>, fix: impl Into<String>) {
+        println!("  ❌ {}", message.as_ref());
+        self.problems += 1;
+        self.fixes.push(fix.into());
+    }
+}
+
+/// Check every external dependency a conversion with these options would need.
+pub fn run(args: &PipelineArgs) -> Result<()> {
+    println!("🩺 RustOCR2md doctor");
+    println!("================================================");
+
+    let loaded = args.load_config()?;
+    crate::print_config_sources(&loaded);
+    let config = &loaded.config;
+    println!(
+        "⚙️  Engine: {:?}, Languages: {}, Math OCR: {}, Language detection: {}",
+        config.engine, config.languages, config.math_ocr, config.detect_language
+    );
+
+    let mut report = Report::default();
+    check_pdfium(&mut report);
+    check_tessdata(&mut report, config);
+    check_paddle(&mut report, config);
+    check_cache(&mut report, config);
+
+    if !report.fixes.is_empty() {
+        println!("\n🔧 How to fix:");
+        for (idx, fix) in report.fixes.iter().enumerate() {
+            println!("  {}. {}", idx + 1, fix);
+        }
+    }
+
+    if report.problems > 0 {
+        anyhow::bail!("doctor found {} problem(s)", report.problems);
+    }
+    println!("\n✨ Everything needed for this configuration is in place.");
+    Ok(())
+}
+
+fn check_pdfium(report: &mut Report) {
+    report.section("PDFium");
+    let name = Pdfium::pdfium_platform_library_name();
+
+    let bound = Pdfium::bind_to_system_library()
+        .map(|b| (format!("system library {}", name.to_string_lossy()), b))
+        .or_else(|e| {
+            Pdfium::bind_to_library("pdfium")
+                .map(|b| ("./pdfium".to_string(), b))
+                .map_err(|_| e)
+        });
+
+    match bound {
+        // PDFium has no call that reports its own build; only the API the bindings use is known
+        Ok((location, bindings)) => report.ok(format!(
+            "Loaded {} (library version not reported; bindings use PDFium API {:?})",
+            location,
+            bindings.version()
+        )),
+        Err(e) => {
+            // A copy that exists but is not on the search path is the common case
+            let strays: Vec<PathBuf> = [exe_dir(), std::env::current_dir().ok()]
+                .into_iter()
+                .flatten()
+                .map(|dir| Pdfium::pdfium_platform_library_name_at_path(&dir))
+                .filter(|path| path.is_file())
+                .collect();
+            for path in &strays {
+                println!("     found {} but it could not be loaded", path.display());
+            }
+
+            let fix = if cfg!(windows) {
+                format!(
+                    "Run download_pdfium.ps1, or place {} next to rust-ocr2md.exe",
+                    name.to_string_lossy()
+                )
+            } else {
+                format!(
+                    "Download {} from https://github.com/bblanchon/pdfium-binaries and put it in a \
+                     library directory (e.g. /usr/local/lib) or on LD_LIBRARY_PATH / DYLD_LIBRARY_PATH",
+                    name.to_string_lossy()
+                )
+            };
+            report.fail(format!("PDFium library not loadable: {}", e), fix);
+        }
+    }
+}
+
+fn check_tessdata(report: &mut Report, config: &Config) {
+    report.section("Tesseract");
+    let dir = OcrEngine::get_tessdata_dir();
+    let origin = if std::env::var_os("TESSDATA_PREFIX").is_some() {
+        "from TESSDATA_PREFIX"
+    } else {
+        "platform default"
+    };
+    let required = ocr_engine::required_tesseract_languages(config);
+
+    if dir.is_dir() {
+        report.ok(format!("tessdata: {} ({})", dir.display(), origin));
+    } else if required.is_empty() {
+        report.ok(format!(
+            "tessdata: {} ({}, not present; not needed for this configuration)",
+            dir.display(),
+            origin
+        ));
+        return;
+    } else {
+        report.fail(
+            format!(
+                "tessdata directory does not exist: {} ({})",
+                dir.display(),
+                origin
+            ),
+            tessdata_fix(&dir, &required),
+        );
+        return;
+    }
+
+    // Requested languages are checked too; they only matter when Tesseract is the engine
+    let mut languages = required.clone();
+    for lang in config.languages.split('+').map(str::trim) {
+        if !lang.is_empty() && !languages.iter().any(|l| l == lang) {
+            languages.push(lang.to_string());
+        }
+    }
+
+    let mut missing = Vec::new();
+    for lang in &languages {
+        let path = dir.join(format!("{}.traineddata", lang));
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 {
+            report.ok(format!("{}.traineddata ({})", lang, format_size(size)));
+        } else if required.contains(lang) {
+            println!("  ❌ {}.traineddata missing", lang);
+            missing.push(lang.clone());
+        } else {
+            println!(
+                "  ⚠️  {}.traineddata missing (only used with --engine tesseract)",
+                lang
+            );
+        }
+    }
+
+    if !missing.is_empty() {
+        report.problems += missing.len();
+        report.fixes.push(tessdata_fix(&dir, &missing));
+        return;
+    }
+
+    if !required.is_empty() {
+        let api = tesseract_rs::TesseractAPI::new();
+        match api.init(dir.to_str().unwrap_or("."), &required.join("+")) {
+            Ok(_) => report.ok(format!("Tesseract initializes with {}", required.join("+"))),
+            Err(e) => report.fail(
+                format!(
+                    "Tesseract failed to initialize with {}: {:?}",
+                    required.join("+"),
+                    e
+                ),
+                "Re-download the traineddata files; a truncated download cannot be loaded",
+            ),
+        }
+    }
+}
+
+fn tessdata_fix(dir: &Path, languages: &[String]) -> String {
+    let files: Vec<String> = languages
+        .iter()
+        .map(|l| format!("{}.traineddata", l))
+        .collect();
+    format!(
+        "Download {} from https://github.com/tesseract-ocr/tessdata into {} \
+         (download_tessdata.ps1 does this on Windows), or set TESSDATA_PREFIX to a directory \
+         that has them",
+        files.join(", "),
+        dir.display()
+    )
+}
+
+fn check_paddle(report: &mut Report, config: &Config) {
+    report.section("PaddleOCR models");
+    let dir = ocr_engine::paddle_model_dir(config.paddle_model_dir.clone());
+    let needed = config.engine == EngineKind::Paddle;
+    println!("  📁 {}", dir.display());
+
+    let expected = expected_digests(&dir);
+    let mut missing = Vec::new();
+    for name in PADDLE_MODEL_FILES {
+        let path = dir.join(name);
+        match sha256_file(&path) {
+            Ok((size, digest)) if size > 0 => {
+                let found = format!("{} ({}, sha256 {})", name, format_size(size), digest);
+                match expected.as_ref().map(|digests| digests.get(name)) {
+                    None => report.ok(format!("{}, not verified: no SHA256SUMS", found)),
+                    Some(Some(want)) if *want == digest => {
+                        report.ok(format!("{}, matches SHA256SUMS", found))
+                    }
+                    Some(want) => {
+                        let message = match want {
+                            Some(want) => format!("{} does not match SHA256SUMS ({})", found, want),
+                            None => format!("{} is not listed in SHA256SUMS", found),
+                        };
+                        let fix = format!("Download {} again into {}", name, dir.display());
+                        if needed {
+                            report.fail(message, fix);
+                        } else {
+                            report.warn(message, fix);
+                        }
+                    }
+                }
+            }
+            _ => {
+                let icon = if needed { "❌" } else { "⚠️ " };
+                println!("  {} {} missing", icon, name);
+                missing.push(name);
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        let fix = format!(
+            "Download the PP-OCRv4 ONNX models ({}) into {} or pass --paddle-model-dir",
+            missing.join(", "),
+            dir.display()
+        );
+        if needed {
+            report.problems += missing.len();
+            report.fixes.push(fix);
+        } else {
+            report
+                .fixes
+                .push(format!("{} (only needed for --engine paddle)", fix));
+        }
+    }
+
+    if let Some(math_dir) = &config.math_model_dir {
+        if math_dir.is_dir() {
+            report.ok(format!("Math model directory: {}", math_dir.display()));
+        } else {
+            report.warn(
+                format!(
+                    "Math model directory does not exist: {}",
+                    math_dir.display()
+                ),
+                "Fix --math-model-dir or remove it",
+            );
+        }
+    }
+}
+
+fn check_cache(report: &mut Report, config: &Config) {
+    report.section("Cache");
+    let dir = &config.cache.dir;
+    if !config.cache.enabled {
+        report.ok(format!("Disabled ({})", dir.display()));
+        return;
+    }
+
+    let probe = dir.join(".doctor-write-test");
+    let writable = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe));
+    match writable {
+        Ok(()) => report.ok(format!("{} is writable", dir.display())),
+        Err(e) => report.fail(
+            format!("{} is not writable: {}", dir.display(), e),
+            "Point --cache-dir (or `cache.dir` in the config file) at a writable directory, or run with --cache false",
+        ),
+    }
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+}
+
+/// Digests by file name from `SHA256SUMS` in `dir`, in `sha256sum` output format.
+fn expected_digests(dir: &Path) -> Option<HashMap<String, String>> {
+    let contents = std::fs::read_to_string(dir.join("SHA256SUMS")).ok()?;
+    Some(
+        contents
+            .lines()
+            .filter_map(|line| {
+                let (digest, name) = line.trim().split_once(char::is_whitespace)?;
+                let name = name.trim().trim_start_matches('*');
+                Some((name.to_string(), digest.to_ascii_lowercase()))
+            })
+            .collect(),
+    )
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<(u64, String)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut file, &mut hasher)?;
+    Ok((size, hex::encode(hasher.finalize())))
+}
@@ -1,10 +1,22 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use thiserror::Error;
 
+/// Exit code when output was written but some pages or files failed.
+pub const EXIT_PARTIAL: i32 = 2;
+/// Exit code for any other failure, including a run in which nothing converted.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code of a run stopped by Ctrl-C (128 + SIGINT).
+pub const EXIT_INTERRUPTED: i32 = 130;
+
 #[derive(Error, Debug)]
 pub enum OcrError {
     #[error("PDF processing error: {0}")]
     PdfError(String),
 
+    #[error("Encrypted PDF: {0}")]
+    EncryptedPdf(String),
+
     #[error("OCR engine error: {0}")]
     OcrEngineError(String),
 
@@ -16,6 +28,93 @@ pub enum OcrError {
 
     #[error("Invalid configuration: {0}")]
     ConfigError(String),
+
+    #[error("{0}")]
+    PageFailed(PageFailure),
+
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(String),
+
+    #[error("Interrupted: {0}")]
+    Interrupted(String),
+
+    #[error("Partial failure: {0}")]
+    PartialFailure(String),
+
+    #[error("Conversion failed: {0}")]
+    TotalFailure(String),
+}
+
+impl OcrError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            OcrError::PartialFailure(_) => EXIT_PARTIAL,
+            OcrError::Interrupted(_) => EXIT_INTERRUPTED,
+            _ => EXIT_FAILURE,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, OcrError>;
+
+/// Pipeline stage a page was in when it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Render,
+    Cache,
+    Preprocess,
+    Ocr,
+    Figures,
+    /// Not attempted because a resource limit was reached
+    Limits,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::Render => "render",
+            Stage::Cache => "cache",
+            Stage::Preprocess => "preprocess",
+            Stage::Ocr => "ocr",
+            Stage::Figures => "figures",
+            Stage::Limits => "limits",
+        };
+        f.write_str(name)
+    }
+}
+
+/// One page that could not be converted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageFailure {
+    pub page: usize,
+    pub stage: Stage,
+    pub message: String,
+}
+
+impl fmt::Display for PageFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "page {} failed during {}: {}",
+            self.page, self.stage, self.message
+        )
+    }
+}
+
+/// Attach the failing page and stage to an error from one step of the page pipeline.
+pub trait StageContext<T> {
+    fn stage(self, page: usize, stage: Stage) -> Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> StageContext<T> for std::result::Result<T, E> {
+    fn stage(self, page: usize, stage: Stage) -> Result<T> {
+        self.map_err(|e| {
+            OcrError::PageFailed(PageFailure {
+                page,
+                stage,
+                message: format!("{:#}", e.into()),
+            })
+        })
+    }
+}
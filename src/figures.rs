@@ -0,0 +1,422 @@
+use crate::ocr_engine::{BlockType, BoundingBox, OcrBlock};
+use image::DynamicImage;
+use std::collections::VecDeque;
+
+/// Luma value below which a pixel counts as ink.
+const INK_LUMA: u8 = 200;
+/// Fraction of ink pixels that marks a grid cell as non-empty.
+const CELL_INK_RATIO: f32 = 0.02;
+/// Regions covering more of the page than this are treated as scanned backgrounds, not figures.
+const MAX_PAGE_COVERAGE: f32 = 0.8;
+/// Regions smaller than this fraction of the page are ignored.
+const MIN_PAGE_COVERAGE: f32 = 0.015;
+/// Text blocks below this confidence are assumed to be OCR noise from graphics.
+const TEXT_MASK_CONFIDENCE: f32 = 0.5;
+
+/// Find non-text regions in a rendered page.
+///
+/// The page is divided into a coarse grid; cells containing ink that is not covered by a
+/// recognized text block are grouped into connected components. `hints` are regions already
+/// known to hold images (e.g. Pdfium image objects) and are merged with the raster result.
+pub fn detect_regions(
+    image: &DynamicImage,
+    blocks: &[OcrBlock],
+    hints: &[BoundingBox],
+) -> Vec<BoundingBox> {
+    let (width, height) = (image.width(), image.height());
+    let page_area = (width as f32 * height as f32).max(1.0);
+
+    let mut regions: Vec<BoundingBox> = hints
+        .iter()
+        .filter(|b| coverage(b, page_area) <= MAX_PAGE_COVERAGE)
+        .cloned()
+        .collect();
+
+    // Without per-block boxes (Tesseract page mode) every text line would look like a figure.
+    let has_layout = !blocks.is_empty() && blocks.iter().all(|b| b.bbox.is_some());
+    if has_layout {
+        regions.extend(
+            raster_regions(image, blocks)
+                .into_iter()
+                .filter(|b| {
+                    let c = coverage(b, page_area);
+                    (MIN_PAGE_COVERAGE..=MAX_PAGE_COVERAGE).contains(&c)
+                }),
+        );
+    }
+
+    merge_overlapping(regions)
+}
+
+/// Replace OCR noise inside figure regions with figure blocks, attaching nearby captions.
+///
+/// `asset_name` maps the figure index (1-based, in reading order) to the asset file name.
+pub fn attach_figures(
+    blocks: &mut Vec<OcrBlock>,
+    regions: &[BoundingBox],
+    image_height: u32,
+    asset_name: impl Fn(usize) -> String,
+) {
+    if regions.is_empty() {
+        return;
+    }
+
+    let mut regions = regions.to_vec();
+    regions.sort_by_key(|r| (r.y, r.x));
+
+    blocks.retain(|b| match &b.bbox {
+        Some(bbox) => !regions.iter().any(|r| contains_center(r, bbox)),
+        None => true,
+    });
+
+    let caption_gap = image_height / 10;
+    for (idx, region) in regions.iter().enumerate() {
+        let caption = take_caption(blocks, region, caption_gap);
+        let figure = OcrBlock {
+            text: caption.unwrap_or_default(),
+            confidence: 1.0,
+            bbox: Some(region.clone()),
+            block_type: BlockType::Figure,
+            language: None,
+            image: Some(asset_name(idx + 1)),
+        };
+        insert_in_reading_order(blocks, figure);
+    }
+}
+
+/// Returns true if the line looks like "Figure 3: ...", "Fig. 3 ..." or "图 3 ...".
+pub fn looks_like_caption(text: &str) -> bool {
+    let lower = text.trim().to_lowercase();
+    let rest = ["figure", "fig.", "fig", "图"]
+        .iter()
+        .find_map(|prefix| lower.strip_prefix(prefix));
+    match rest {
+        Some(rest) => rest
+            .trim_start()
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+fn take_caption(blocks: &mut Vec<OcrBlock>, region: &BoundingBox, max_gap: u32) -> Option<String> {
+    let region_bottom = region.y + region.height;
+    let position = blocks.iter().position(|b| {
+        let Some(bbox) = &b.bbox else {
+            return false;
+        };
+        if b.block_type != BlockType::Text || !looks_like_caption(&b.text) {
+            return false;
+        }
+        let below = bbox.y >= region_bottom && bbox.y - region_bottom <= max_gap;
+        let above = bbox.y + bbox.height <= region.y && region.y - (bbox.y + bbox.height) <= max_gap;
+        (below || above) && overlaps_horizontally(region, bbox)
+    })?;
+    let block = blocks.remove(position);
+    Some(block.text.trim().to_string())
+}
+
+fn insert_in_reading_order(blocks: &mut Vec<OcrBlock>, block: OcrBlock) {
+    let top = block.bbox.as_ref().map(|b| b.y).unwrap_or(0);
+    let position = blocks
+        .iter()
+        .position(|b| b.bbox.as_ref().is_some_and(|bbox| bbox.y > top))
+        .unwrap_or(blocks.len());
+    blocks.insert(position, block);
+}
+
+fn raster_regions(image: &DynamicImage, blocks: &[OcrBlock]) -> Vec<BoundingBox> {
+    let gray = image.to_luma8();
+    let (width, height) = gray.dimensions();
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+    let cell = (width / 80).max(8);
+    let cols = width.div_ceil(cell) as usize;
+    let rows = height.div_ceil(cell) as usize;
+
+    let mut ink = vec![false; cols * rows];
+    for row in 0..rows {
+        for col in 0..cols {
+            let x0 = col as u32 * cell;
+            let y0 = row as u32 * cell;
+            let x1 = (x0 + cell).min(width);
+            let y1 = (y0 + cell).min(height);
+            let mut dark = 0u32;
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    if gray.get_pixel(x, y)[0] < INK_LUMA {
+                        dark += 1;
+                    }
+                }
+            }
+            let total = ((x1 - x0) * (y1 - y0)).max(1);
+            ink[row * cols + col] = dark as f32 / total as f32 > CELL_INK_RATIO;
+        }
+    }
+
+    // Mask out cells that belong to confidently recognized text.
+    for block in blocks {
+        if block.confidence < TEXT_MASK_CONFIDENCE {
+            continue;
+        }
+        if let Some(bbox) = &block.bbox {
+            let c0 = (bbox.x / cell) as usize;
+            let r0 = (bbox.y / cell) as usize;
+            let c1 = ((bbox.x + bbox.width) / cell) as usize;
+            let r1 = ((bbox.y + bbox.height) / cell) as usize;
+            for row in r0..=r1.min(rows - 1) {
+                for col in c0..=c1.min(cols - 1) {
+                    ink[row * cols + col] = false;
+                }
+            }
+        }
+    }
+
+    let mut seen = vec![false; cols * rows];
+    let mut regions = Vec::new();
+    for start in 0..ink.len() {
+        if !ink[start] || seen[start] {
+            continue;
+        }
+        let (mut min_c, mut min_r, mut max_c, mut max_r) = (cols, rows, 0, 0);
+        let mut queue = VecDeque::from([start]);
+        seen[start] = true;
+        while let Some(idx) = queue.pop_front() {
+            let (row, col) = (idx / cols, idx % cols);
+            min_c = min_c.min(col);
+            max_c = max_c.max(col);
+            min_r = min_r.min(row);
+            max_r = max_r.max(row);
+
+            let mut neighbors = Vec::with_capacity(4);
+            if col > 0 {
+                neighbors.push(idx - 1);
+            }
+            if col + 1 < cols {
+                neighbors.push(idx + 1);
+            }
+            if row > 0 {
+                neighbors.push(idx - cols);
+            }
+            if row + 1 < rows {
+                neighbors.push(idx + cols);
+            }
+            for n in neighbors {
+                if ink[n] && !seen[n] {
+                    seen[n] = true;
+                    queue.push_back(n);
+                }
+            }
+        }
+
+        if max_c - min_c < 3 || max_r - min_r < 3 {
+            continue;
+        }
+        let x = min_c as u32 * cell;
+        let y = min_r as u32 * cell;
+        regions.push(BoundingBox {
+            x,
+            y,
+            width: ((max_c as u32 + 1) * cell).min(width) - x,
+            height: ((max_r as u32 + 1) * cell).min(height) - y,
+        });
+    }
+
+    regions
+}
+
+fn merge_overlapping(mut regions: Vec<BoundingBox>) -> Vec<BoundingBox> {
+    let mut merged = true;
+    while merged {
+        merged = false;
+        'outer: for i in 0..regions.len() {
+            for j in (i + 1)..regions.len() {
+                if intersects(&regions[i], &regions[j]) {
+                    let other = regions.remove(j);
+                    regions[i] = union(&regions[i], &other);
+                    merged = true;
+                    break 'outer;
+                }
+            }
+        }
+    }
+    regions
+}
+
+fn coverage(bbox: &BoundingBox, page_area: f32) -> f32 {
+    (bbox.width as f32 * bbox.height as f32) / page_area
+}
+
+fn intersects(a: &BoundingBox, b: &BoundingBox) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+fn overlaps_horizontally(a: &BoundingBox, b: &BoundingBox) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width
+}
+
+fn contains_center(region: &BoundingBox, bbox: &BoundingBox) -> bool {
+    let cx = bbox.x + bbox.width / 2;
+    let cy = bbox.y + bbox.height / 2;
+    cx >= region.x && cx < region.x + region.width && cy >= region.y && cy < region.y + region.height
+}
+
+fn union(a: &BoundingBox, b: &BoundingBox) -> BoundingBox {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    BoundingBox {
+        x,
+        y,
+        width: (a.x + a.width).max(b.x + b.width) - x,
+        height: (a.y + a.height).max(b.y + b.height) - y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    fn bbox(x: u32, y: u32, width: u32, height: u32) -> BoundingBox {
+        BoundingBox {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn dims(bbox: &BoundingBox) -> (u32, u32, u32, u32) {
+        (bbox.x, bbox.y, bbox.width, bbox.height)
+    }
+
+    fn text_block(text: &str, bbox: Option<BoundingBox>) -> OcrBlock {
+        OcrBlock {
+            text: text.to_string(),
+            confidence: 0.9,
+            bbox,
+            block_type: BlockType::Text,
+            language: None,
+            image: None,
+        }
+    }
+
+    /// A white page with solid ink inside each rectangle.
+    fn page(width: u32, height: u32, ink: &[BoundingBox]) -> DynamicImage {
+        let gray = GrayImage::from_fn(width, height, |x, y| {
+            let inked = ink
+                .iter()
+                .any(|b| (b.x..b.x + b.width).contains(&x) && (b.y..b.y + b.height).contains(&y));
+            Luma([if inked { 0 } else { 255 }])
+        });
+        DynamicImage::ImageLuma8(gray)
+    }
+
+    fn texts(blocks: &[OcrBlock]) -> Vec<&str> {
+        blocks.iter().map(|b| b.text.as_str()).collect()
+    }
+
+    #[test]
+    fn merges_chains_of_overlapping_regions() {
+        let merged = merge_overlapping(vec![
+            bbox(0, 0, 10, 10),
+            bbox(50, 50, 10, 10),
+            bbox(5, 5, 10, 10),
+            bbox(12, 12, 10, 10),
+        ]);
+        let merged: Vec<_> = merged.iter().map(dims).collect();
+        assert_eq!(merged, vec![(0, 0, 22, 22), (50, 50, 10, 10)]);
+    }
+
+    #[test]
+    fn keeps_touching_regions_apart() {
+        let merged = merge_overlapping(vec![bbox(0, 0, 10, 10), bbox(10, 0, 10, 10)]);
+        assert_eq!(merged.len(), 2);
+    }
+
+    #[test]
+    fn detects_ink_outside_text_blocks() {
+        let image = page(
+            800,
+            800,
+            &[bbox(100, 100, 200, 150), bbox(100, 500, 600, 40)],
+        );
+        let blocks = [text_block("a paragraph", Some(bbox(100, 500, 600, 40)))];
+        let regions = detect_regions(&image, &blocks, &[]);
+        assert_eq!(regions.len(), 1);
+        let (x, y, width, height) = dims(&regions[0]);
+        assert!(x <= 100 && y <= 100);
+        assert!(x + width >= 300 && y + height >= 250);
+        assert!(y + height < 500);
+    }
+
+    #[test]
+    fn needs_block_boxes_for_raster_detection() {
+        let image = page(800, 800, &[bbox(100, 100, 200, 150)]);
+        let blocks = [text_block("page text", None)];
+        assert!(detect_regions(&image, &blocks, &[]).is_empty());
+
+        // Hints still count, merged with each other
+        let hints = [bbox(100, 100, 100, 100), bbox(150, 150, 100, 100)];
+        let regions = detect_regions(&image, &blocks, &hints);
+        let regions: Vec<_> = regions.iter().map(dims).collect();
+        assert_eq!(regions, vec![(100, 100, 150, 150)]);
+    }
+
+    #[test]
+    fn ignores_page_sized_hints() {
+        let image = page(100, 100, &[]);
+        assert!(detect_regions(&image, &[], &[bbox(0, 0, 100, 95)]).is_empty());
+    }
+
+    #[test]
+    fn inserts_before_the_first_lower_block() {
+        let mut blocks = vec![
+            text_block("top", Some(bbox(0, 0, 100, 10))),
+            text_block("middle", Some(bbox(0, 100, 100, 10))),
+            text_block("bottom", Some(bbox(0, 300, 100, 10))),
+        ];
+        insert_in_reading_order(
+            &mut blocks,
+            text_block("figure", Some(bbox(0, 200, 50, 50))),
+        );
+        insert_in_reading_order(&mut blocks, text_block("first", Some(bbox(0, 0, 50, 5))));
+        insert_in_reading_order(&mut blocks, text_block("last", Some(bbox(0, 900, 50, 50))));
+        assert_eq!(
+            texts(&blocks),
+            vec!["top", "first", "middle", "figure", "bottom", "last"]
+        );
+    }
+
+    #[test]
+    fn replaces_noise_with_figures_and_takes_captions() {
+        let mut blocks = vec![
+            text_block("Intro", Some(bbox(0, 0, 500, 20))),
+            text_block("~~ noise ~~", Some(bbox(120, 120, 50, 20))),
+            text_block("Figure 1: A plot", Some(bbox(100, 310, 300, 20))),
+            text_block("Body text", Some(bbox(0, 400, 500, 20))),
+        ];
+        attach_figures(&mut blocks, &[bbox(100, 100, 300, 200)], 1000, |n| {
+            format!("fig-{}.png", n)
+        });
+
+        assert_eq!(
+            texts(&blocks),
+            vec!["Intro", "Figure 1: A plot", "Body text"]
+        );
+        let figure = &blocks[1];
+        assert_eq!(figure.block_type, BlockType::Figure);
+        assert_eq!(figure.image.as_deref(), Some("fig-1.png"));
+    }
+
+    #[test]
+    fn recognizes_caption_prefixes() {
+        assert!(looks_like_caption("Figure 3: Results"));
+        assert!(looks_like_caption("Fig. 12 overview"));
+        assert!(looks_like_caption("图 2 系统结构"));
+        assert!(!looks_like_caption("Figures show that"));
+        assert!(!looks_like_caption("Configuration 1"));
+    }
+}
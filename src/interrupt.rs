@@ -0,0 +1,32 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use rust_ocr2md::error::EXIT_INTERRUPTED;
+
+static REQUESTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Handle Ctrl-C: the first press lets the current page finish and the partial output be
+/// written, the second exits immediately.
+pub fn install() {
+    let requested = flag();
+    let result = ctrlc::set_handler(move || {
+        if requested.swap(true, Ordering::SeqCst) {
+            eprintln!("\n🛑 Interrupted again, exiting now");
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        eprintln!("\n⏸️  Interrupt received: finishing the current page, then writing partial output (Ctrl-C again to quit now)");
+    });
+    if let Err(e) = result {
+        eprintln!("⚠️  Ctrl-C handling unavailable: {}", e);
+    }
+}
+
+/// Set by the first Ctrl-C; handed to the converter as its cancel flag.
+pub fn flag() -> Arc<AtomicBool> {
+    REQUESTED.get_or_init(Arc::default).clone()
+}
+
+/// Whether Ctrl-C has been pressed.
+pub fn requested() -> bool {
+    flag().load(Ordering::SeqCst)
+}
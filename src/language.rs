@@ -1,5 +1,6 @@
 use whatlang::{Detector, Lang};
 
+#[derive(Clone)]
 pub struct LanguageDetector {
     detector: Detector,
 }
@@ -0,0 +1,46 @@
+//! PDF and image to Markdown conversion with OCR.
+//!
+//! [`Converter`] is the entry point: build one from a [`Config`], optionally attach a
+//! [`ProgressHandler`], then convert files or in-memory PDFs into [`Document`]s.
+//!
+//! ```no_run
+//! use rust_ocr2md::{Config, Converter, Event};
+//!
+//! let converter = Converter::new(Config::default()).with_progress(|event: &Event| {
+//!     if let Event::PageFinished { page, .. } = event {
+//!         eprintln!("page {} done", page);
+//!     }
+//! });
+//! let document = converter.convert_bytes(std::fs::read("paper.pdf")?)?;
+//! std::fs::write("paper.md", &document.markdown)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+mod analysis;
+pub mod annotations;
+pub mod cache;
+pub mod config;
+mod converter;
+mod debug;
+pub mod error;
+mod figures;
+mod image_processor;
+mod language;
+mod layout;
+pub mod manifest;
+pub mod markdown_builder;
+mod math;
+pub mod ocr_engine;
+pub mod outline;
+mod page_range;
+pub mod page_source;
+pub mod pdf_processor;
+pub mod progress;
+pub mod report;
+pub mod saved;
+
+pub use config::Config;
+pub use converter::{ConvertOptions, Converter, Document};
+pub use error::{OcrError, PageFailure, Stage};
+pub use pdf_processor::PageResult;
+pub use progress::{Event, ProgressHandler};
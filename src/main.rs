@@ -1,65 +1,133 @@
-mod pdf_processor;
-mod ocr_engine;
-mod image_processor;
-mod markdown_builder;
-mod config;
-mod error;
-mod cache;
-mod language;
-mod layout;
-mod math;
-
-use anyhow::Result;
-use clap::Parser;
-use indicatif::{ProgressBar, ProgressStyle};
-use std::path::PathBuf;
+mod batch;
+mod cache_cli;
+mod console;
+mod doctor;
+mod interrupt;
+mod render;
+mod serve;
+mod units;
+mod watch;
+
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Read, Write};
 
-use crate::config::{CacheConfig, Config, EngineKind};
-use crate::pdf_processor::PdfProcessor;
-use crate::ocr_engine::OcrEngine;
-use crate::markdown_builder::MarkdownBuilder;
+use rust_ocr2md::config::{self, Config, EngineKind, ErrorPolicy, LoadedConfig};
+use rust_ocr2md::error::{self, OcrError, PageFailure};
+use rust_ocr2md::pdf_processor::PASSWORD_ENV;
+use rust_ocr2md::progress::Event;
+use rust_ocr2md::report::{DocumentReport, RunReport, Status};
+use rust_ocr2md::{ConvertOptions, Converter};
+
+use crate::console::{Console, ProgressMode};
 
 #[derive(Parser)]
 #[command(name = "RustOCR2md")]
 #[command(about = "High-performance PDF to Markdown converter with OCR\n\nUsage: Drag and drop a PDF file onto this executable, or run from command line.", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
-    /// Input PDF file path (can be provided by dragging file onto exe)
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    convert: ConvertArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert every PDF/image under directories or globs, mirroring the tree into --out
+    Batch(batch::BatchArgs),
+    /// Inspect and clean up the on-disk cache
+    #[command(subcommand)]
+    Cache(cache_cli::CacheCommand),
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Check PDFium, tessdata, Paddle models and the cache directory, with fix instructions
+    Doctor(PipelineArgs),
+    /// Build Markdown, HTML or text again from saved OCR results, without running OCR
+    Render(render::RenderArgs),
+    /// Serve a local HTTP job API, with the OCR models loaded once for every request
+    Serve(serve::ServeArgs),
+    /// Convert files dropped into a folder, moving the originals to done/failed folders
+    Watch(watch::WatchArgs),
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration after merging files, profile and flags
+    Show(PipelineArgs),
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// Input PDF, image, multi-page TIFF or directory of images, or - for a PDF on stdin (can be provided by dragging onto exe)
     #[arg(index = 1)]
     input: Option<PathBuf>,
 
-    /// Output markdown file path
+    /// Output markdown file path, or - to stream it to stdout
     #[arg(short, long)]
     output: Option<PathBuf>,
 
+    /// Directory for extracted figures (default: "assets" next to the output file)
+    #[arg(long)]
+    assets_dir: Option<PathBuf>,
+
+    /// Pages to convert, e.g. "all", "1-10,15", "10-", "-5", "last-2-last", "odd", "1-100,!50-60", "iii-x"
+    #[arg(long, default_value = "all", allow_hyphen_values = true)]
+    pages: String,
+
+    /// Also save the OCR results as JSON, to rebuild the Markdown later with `render`
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+}
+
+/// Options shared by single-file and batch conversion.
+///
+/// Unset options fall back to the config file, the selected profile, then built-in defaults.
+#[derive(Args)]
+struct PipelineArgs {
+    /// Config file to use instead of the discovered user/project files
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Named profile from the config files or built in: paper, book, scan-cjk
+    #[arg(long)]
+    profile: Option<String>,
+
     /// Number of threads (default: CPU cores)
     #[arg(short, long)]
     threads: Option<usize>,
 
     /// DPI for PDF rendering (default: 300)
-    #[arg(short, long, default_value = "300")]
-    dpi: u32,
+    #[arg(short, long)]
+    dpi: Option<u32>,
 
     /// Languages for OCR (default: eng+chi_sim+equ)
-    #[arg(short, long, default_value = "eng+chi_sim+equ")]
-    languages: String,
+    #[arg(short, long)]
+    languages: Option<String>,
 
-    /// OCR backend: tesseract or paddle
-    #[arg(long, value_enum, default_value = "paddle")]
-    engine: EngineKind,
+    /// OCR backend: tesseract or paddle (default: paddle)
+    #[arg(long, value_enum)]
+    engine: Option<EngineKind>,
 
-    /// Enable PP-Structure style layout analysis
-    #[arg(long, default_value = "true")]
-    layout: bool,
+    /// Enable PP-Structure style layout analysis (default: true)
+    #[arg(long)]
+    layout: Option<bool>,
 
-    /// Enable language detection and dynamic model switch
-    #[arg(long, default_value = "true")]
-    detect_language: bool,
+    /// Enable language detection and dynamic model switch (default: true)
+    #[arg(long)]
+    detect_language: Option<bool>,
 
-    /// Enable math OCR for formulas
-    #[arg(long, default_value = "true")]
-    math_ocr: bool,
+    /// Enable math OCR for formulas (default: true)
+    #[arg(long)]
+    math_ocr: Option<bool>,
 
     /// PaddleOCR model directory (det/cls/rec ONNX files)
     #[arg(long)]
@@ -69,73 +137,340 @@ struct Cli {
     #[arg(long)]
     math_model_dir: Option<PathBuf>,
 
-    /// Enable on-disk cache (preprocess + OCR)
-    #[arg(long, default_value = "true")]
-    cache: bool,
+    /// Enable on-disk cache (preprocess + OCR) (default: true)
+    #[arg(long)]
+    cache: Option<bool>,
 
-    /// Cache preprocessed images
-    #[arg(long, default_value = "true")]
-    cache_preprocess: bool,
+    /// Cache preprocessed images (default: true)
+    #[arg(long)]
+    cache_preprocess: Option<bool>,
 
-    /// Cache OCR results
-    #[arg(long, default_value = "true")]
-    cache_ocr: bool,
+    /// Cache OCR results (default: true)
+    #[arg(long)]
+    cache_ocr: Option<bool>,
 
     /// Override cache directory
     #[arg(long)]
     cache_dir: Option<PathBuf>,
 
-    /// Prefer GPU acceleration (if supported by ONNX Runtime)
+    /// Cache size limit in MB, least recently used pages are removed beyond it, 0 = no limit (default: 2048)
+    #[arg(long)]
+    cache_max_size_mb: Option<u64>,
+
+    /// Prefer GPU acceleration (if supported by ONNX Runtime) (default: false)
+    #[arg(long)]
+    use_gpu: Option<bool>,
+
+    /// Auto-tune pipeline based on document type (default: true)
+    #[arg(long)]
+    auto_config: Option<bool>,
+
+    /// Enable image preprocessing for better accuracy (default: true)
+    #[arg(long)]
+    preprocess: Option<bool>,
+
+    /// Extract figures and embedded images as Markdown image assets (default: true)
+    #[arg(long)]
+    figures: Option<bool>,
+
+    #[command(flatten)]
+    markdown: MarkdownArgs,
+
+    /// What to do with a page that fails: skip, placeholder or abort (default: placeholder)
+    #[arg(long, value_enum)]
+    on_error: Option<ErrorPolicy>,
+
+    /// Largest page image in pixels; bigger pages get a lower DPI, 0 = no limit (default: 50000000)
+    #[arg(long)]
+    max_page_pixels: Option<u64>,
+
+    /// Seconds a single page may take before it counts as failed, 0 = no limit (default: 300)
+    #[arg(long)]
+    page_timeout: Option<u64>,
+
+    /// Most pages to convert from one document, 0 = no limit (default: 10000)
+    #[arg(long)]
+    max_pages: Option<usize>,
+
+    /// Reuse the pages an interrupted or partly failed run recorded next to the output
     #[arg(long, default_value = "false")]
-    use_gpu: bool,
+    resume: bool,
 
-    /// Auto-tune pipeline based on document type
-    #[arg(long, default_value = "true")]
-    auto_config: bool,
+    /// Password for encrypted PDFs (or set RUST_OCR2MD_PDF_PASSWORD)
+    #[arg(long, conflicts_with = "password_file")]
+    password: Option<String>,
 
-    /// Enable image preprocessing for better accuracy
-    #[arg(long, default_value = "true")]
-    preprocess: bool,
+    /// Read the PDF password from the first line of a file
+    #[arg(long)]
+    password_file: Option<PathBuf>,
 
-    /// Page range (e.g., 1-10, or "all")
-    #[arg(long, default_value = "all")]
-    pages: String,
+    /// Never wait for Enter before exiting (implied when stdin/stdout is not a terminal)
+    #[arg(long, default_value = "false")]
+    no_pause: bool,
+
+    /// Progress output: bar, or json for JSON-lines events on stderr
+    #[arg(long, value_enum, default_value = "bar")]
+    progress: ProgressMode,
+
+    /// Write per-page timings, confidence, warnings and errors as JSON to this file
+    #[arg(long)]
+    report: Option<PathBuf>,
+
+    /// Write each page with its detected blocks drawn, plus formula crops, to this directory
+    #[arg(long)]
+    debug_dir: Option<PathBuf>,
+}
+
+/// Options that only shape the Markdown, shared by conversion and `render`.
+#[derive(Args)]
+struct MarkdownArgs {
+    /// Use PDF bookmarks as the heading hierarchy (default: true)
+    #[arg(long)]
+    outline: Option<bool>,
+
+    /// Generate a linked table of contents from the bookmarks (default: false)
+    #[arg(long)]
+    toc: Option<bool>,
+
+    /// Carry hyperlinks, highlights and comments into the Markdown (default: true)
+    #[arg(long)]
+    annotations: Option<bool>,
+
+    /// Start with the document title and a metadata list (default: true)
+    #[arg(long)]
+    metadata: Option<bool>,
+
+    /// Write each text block on one line instead of keeping OCR line breaks (default: false)
+    #[arg(long)]
+    join_lines: Option<bool>,
+
+    /// Rejoin words hyphenated across line breaks (default: false)
+    #[arg(long)]
+    dehyphenate: Option<bool>,
+
+    /// Join text blocks that continue the previous block's sentence (default: false)
+    #[arg(long)]
+    merge_paragraphs: Option<bool>,
+}
+
+impl MarkdownArgs {
+    fn apply(&self, config: &mut Config, explicit: &mut BTreeSet<String>) {
+        set(&mut config.outline, &self.outline, "outline", explicit);
+        set(&mut config.toc, &self.toc, "toc", explicit);
+        set(&mut config.annotations, &self.annotations, "annotations", explicit);
+        set(&mut config.metadata, &self.metadata, "metadata", explicit);
+        set(&mut config.join_lines, &self.join_lines, "join_lines", explicit);
+        set(&mut config.dehyphenate, &self.dehyphenate, "dehyphenate", explicit);
+        set(&mut config.merge_paragraphs, &self.merge_paragraphs, "merge_paragraphs", explicit);
+    }
+}
+
+/// Apply a flag given on the command line and mark its config key explicit.
+fn set<T: Clone>(target: &mut T, value: &Option<T>, key: &str, explicit: &mut BTreeSet<String>) {
+    if let Some(value) = value {
+        *target = value.clone();
+        explicit.insert(key.to_string());
+    }
+}
+
+impl PipelineArgs {
+    /// Defaults, config files and profile, with the flags given on the command line on top.
+    fn load_config(&self) -> Result<LoadedConfig> {
+        let mut loaded = config::load(self.config.as_deref(), self.profile.as_deref())?;
+        self.apply(&mut loaded);
+        Ok(loaded)
+    }
+
+    fn apply(&self, loaded: &mut LoadedConfig) {
+        let LoadedConfig {
+            config, explicit, ..
+        } = loaded;
+        set(&mut config.threads, &self.threads, "threads", explicit);
+        set(&mut config.dpi, &self.dpi, "dpi", explicit);
+        set(&mut config.languages, &self.languages, "languages", explicit);
+        set(&mut config.engine, &self.engine, "engine", explicit);
+        set(&mut config.layout, &self.layout, "layout", explicit);
+        set(&mut config.detect_language, &self.detect_language, "detect_language", explicit);
+        set(&mut config.math_ocr, &self.math_ocr, "math_ocr", explicit);
+        if self.paddle_model_dir.is_some() {
+            config.paddle_model_dir = self.paddle_model_dir.clone();
+            explicit.insert("paddle_model_dir".to_string());
+        }
+        if self.math_model_dir.is_some() {
+            config.math_model_dir = self.math_model_dir.clone();
+            explicit.insert("math_model_dir".to_string());
+        }
+        if self.debug_dir.is_some() {
+            config.debug_dir = self.debug_dir.clone();
+            explicit.insert("debug_dir".to_string());
+        }
+        set(&mut config.cache.enabled, &self.cache, "cache.enabled", explicit);
+        set(&mut config.cache.preprocess, &self.cache_preprocess, "cache.preprocess", explicit);
+        set(&mut config.cache.ocr, &self.cache_ocr, "cache.ocr", explicit);
+        set(&mut config.cache.dir, &self.cache_dir, "cache.dir", explicit);
+        set(&mut config.cache.max_size_mb, &self.cache_max_size_mb, "cache.max_size_mb", explicit);
+        set(&mut config.use_gpu, &self.use_gpu, "use_gpu", explicit);
+        set(&mut config.auto_config, &self.auto_config, "auto_config", explicit);
+        set(&mut config.preprocess, &self.preprocess, "preprocess", explicit);
+        set(&mut config.figures, &self.figures, "figures", explicit);
+        self.markdown.apply(config, explicit);
+        set(&mut config.on_error, &self.on_error, "on_error", explicit);
+        set(&mut config.max_page_pixels, &self.max_page_pixels, "max_page_pixels", explicit);
+        set(&mut config.page_timeout, &self.page_timeout, "page_timeout", explicit);
+        set(&mut config.max_pages, &self.max_pages, "max_pages", explicit);
+    }
+
+    /// Password precedence: --password, then --password-file, then the environment.
+    fn password(&self) -> Result<Option<String>> {
+        if self.password.is_some() {
+            return Ok(self.password.clone());
+        }
+        if let Some(path) = &self.password_file {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read password file {}", path.display()))?;
+            let line = contents.lines().next().unwrap_or_default();
+            return Ok(Some(line.to_string()));
+        }
+        Ok(std::env::var(PASSWORD_ENV).ok().filter(|p| !p.is_empty()))
+    }
+
+    /// A converter for the loaded configuration, reporting progress on the terminal and
+    /// stopping at Ctrl-C.
+    fn converter(&self, loaded: &LoadedConfig) -> Result<Converter> {
+        Ok(Converter::new(loaded.config.clone())
+            .with_explicit_keys(loaded.explicit.clone())
+            .with_password(self.password()?)
+            .with_progress(Console::new(self.progress))
+            .with_cancel_flag(interrupt::flag()))
+    }
+
+    /// Prompts and the exit pause are only for drag-and-drop use from a console window.
+    fn interactive(&self) -> bool {
+        !self.no_pause
+            && self.progress != ProgressMode::Json
+            && io::stdin().is_terminal()
+            && io::stdout().is_terminal()
+    }
+}
+
+impl Cli {
+    /// Pipeline options of the command, if it runs the pipeline.
+    fn pipeline(&self) -> Option<&PipelineArgs> {
+        match &self.command {
+            Some(Command::Batch(args)) => Some(&args.pipeline),
+            Some(Command::Cache(_)) => None,
+            Some(Command::Config(ConfigCommand::Show(args))) => Some(args),
+            Some(Command::Doctor(args)) => Some(args),
+            Some(Command::Render(_)) => None,
+            Some(Command::Serve(args)) => Some(&args.pipeline),
+            Some(Command::Watch(args)) => Some(&args.pipeline),
+            None => Some(&self.convert.pipeline),
+        }
+    }
 }
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("\n❌ Error: {}", e);
-        if let Some(source) = e.source() {
-            eprintln!("   Caused by: {}", source);
+    let cli = Cli::parse();
+    let interactive = !matches!(
+        cli.command,
+        Some(Command::Config(_) | Command::Doctor(_) | Command::Serve(_) | Command::Watch(_))
+    ) && cli.pipeline().is_some_and(PipelineArgs::interactive);
+    let progress_mode = cli.pipeline().map_or(ProgressMode::Bar, |p| p.progress);
+    if matches!(cli.command, None | Some(Command::Batch(_) | Command::Watch(_))) {
+        interrupt::install();
+    }
+
+    if let Err(e) = run(cli) {
+        if progress_mode == ProgressMode::Json {
+            console::emit(&Event::Error {
+                message: format!("{:#}", e),
+            });
+        } else if matches!(exit_code(&e), error::EXIT_PARTIAL | error::EXIT_INTERRUPTED) {
+            eprintln!("\n⚠️  {}", e);
+        } else {
+            eprintln!("\n❌ Error: {}", e);
+            if let Some(source) = e.source() {
+                eprintln!("   Caused by: {}", source);
+            }
         }
-        wait_for_enter();
-        std::process::exit(1);
+        if interactive {
+            wait_for_enter();
+        }
+        std::process::exit(exit_code(&e));
     }
 }
 
+/// Partial failures exit with their own code so scripts can tell them from total failure.
+fn exit_code(e: &anyhow::Error) -> i32 {
+    e.downcast_ref::<OcrError>()
+        .map_or(error::EXIT_FAILURE, OcrError::exit_code)
+}
+
 fn wait_for_enter() {
-    println!("\nPress Enter to exit...");
-    let _ = io::stdout().flush();
+    eprintln!("\nPress Enter to exit...");
+    let _ = io::stderr().flush();
     let mut input = String::new();
     let _ = io::stdin().read_line(&mut input);
 }
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
+fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Some(Command::Batch(args)) => batch::run(&args),
+        Some(Command::Cache(command)) => cache_cli::run(&command),
+        Some(Command::Config(ConfigCommand::Show(args))) => show_config(&args),
+        Some(Command::Doctor(args)) => doctor::run(&args),
+        Some(Command::Render(args)) => render::run(&args),
+        Some(Command::Serve(args)) => serve::run(&args),
+        Some(Command::Watch(args)) => watch::run(&args),
+        None => run_single(cli.convert),
+    }
+}
+
+fn show_config(args: &PipelineArgs) -> Result<()> {
+    let loaded = args.load_config()?;
+
+    if loaded.files.is_empty() {
+        println!("# No config files found");
+    }
+    for path in &loaded.files {
+        println!("# File: {}", path.display());
+    }
+    if let Some(profile) = &loaded.profile {
+        println!("# Profile: {}", profile);
+    }
+    println!();
+    print!("{}", toml::to_string_pretty(&loaded.config)?);
+    Ok(())
+}
+
+/// Print which config files and profile a run is using.
+fn print_config_sources(loaded: &LoadedConfig) {
+    for path in &loaded.files {
+        eprintln!("🗂️  Config file: {}", path.display());
+    }
+    if let Some(profile) = &loaded.profile {
+        eprintln!("🧩 Profile: {}", profile);
+    }
+}
+
+fn run_single(cli: ConvertArgs) -> Result<()> {
     let start = Instant::now();
     
     // Get input path - either from argument or prompt user
     let input_path = match cli.input {
         Some(path) => path,
+        None if !cli.pipeline.interactive() => {
+            anyhow::bail!("No input file provided. Usage: rust-ocr2md <INPUT> [OPTIONS]");
+        }
         None => {
-            println!("🚀 RustOCR2md - High-performance PDF OCR Tool");
-            println!("================================================");
-            println!("\nNo input file provided.");
-            println!("Usage: Drag a PDF file onto this executable, or:");
-            println!("       rust-ocr2md.exe <pdf_file>");
-            println!("\nEnter PDF file path (or drag file here):");
-            print!("> ");
-            io::stdout().flush()?;
+            eprintln!("🚀 RustOCR2md - High-performance PDF OCR Tool");
+            eprintln!("================================================");
+            eprintln!("\nNo input file provided.");
+            eprintln!("Usage: Drag a PDF file onto this executable, or:");
+            eprintln!("       rust-ocr2md.exe <pdf_file>");
+            eprintln!("\nEnter PDF file path (or drag file here):");
+            eprint!("> ");
+            io::stderr().flush()?;
             
             let mut input = String::new();
             io::stdin().read_line(&mut input)?;
@@ -150,114 +485,145 @@ fn run() -> Result<()> {
     };
     
     // Validate input file exists
-    if !input_path.exists() {
+    let from_stdin = is_stdin(&input_path);
+    if !from_stdin && !input_path.exists() {
         anyhow::bail!("Input file does not exist: {}", input_path.display());
     }
-    
-    if input_path.extension().map(|e| e.to_ascii_lowercase()) != Some("pdf".into()) {
-        anyhow::bail!("Input file must be a PDF: {}", input_path.display());
+    if from_stdin && cli.pipeline.resume {
+        anyhow::bail!("--resume needs an input file; it cannot be used with stdin input");
     }
 
-    println!("🚀 RustOCR2md - High-performance PDF OCR Tool");
-    println!("================================================");
-
-    // Setup configuration
-    let default_cache_dir = Config::default().cache.dir;
-    let config = Config {
-        dpi: cli.dpi,
-        languages: cli.languages.clone(),
-        detect_language: cli.detect_language,
-        preprocess: cli.preprocess,
-        threads: cli.threads.unwrap_or_else(num_cpus::get),
-        engine: cli.engine,
-        layout: cli.layout,
-        math_ocr: cli.math_ocr,
-        paddle_model_dir: cli.paddle_model_dir.clone(),
-        math_model_dir: cli.math_model_dir.clone(),
-        cache: CacheConfig {
-            enabled: cli.cache,
-            dir: cli.cache_dir.unwrap_or(default_cache_dir),
-            preprocess: cli.cache_preprocess,
-            ocr: cli.cache_ocr,
-        },
-        use_gpu: cli.use_gpu,
-        auto_config: cli.auto_config,
-    };
-
-    println!("📄 Input: {}", input_path.display());
-    println!("⚙️  Config: {} DPI, {} threads, Languages: {}, Engine: {:?}", 
-             config.dpi, config.threads, config.languages, config.engine);
-
-    // Initialize components
-    let pdf_processor = PdfProcessor::new(&input_path, config.dpi)?;
-    let total_pages = pdf_processor.page_count();
-    
-    println!("📖 Total pages: {}", total_pages);
+    eprintln!("🚀 RustOCR2md - High-performance PDF OCR Tool");
+    eprintln!("================================================");
 
-    // Parse page range
-    let page_range = parse_page_range(&cli.pages, total_pages)?;
-    println!("🎯 Processing pages: {:?}", page_range);
-
-    // Setup progress bar
-    let pb = ProgressBar::new(page_range.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")?
-            .progress_chars("█▓▒░ "),
-    );
-
-    // Process pages with OCR
-    let ocr_engine = OcrEngine::new(&config)?;
-    let results = pdf_processor.process_pages(&page_range, &ocr_engine, &config, &pb)?;
-
-    pb.finish_with_message("✅ OCR completed!");
-
-    // Build markdown
-    println!("📝 Building Markdown...");
-    let markdown = MarkdownBuilder::new().build(&results, &input_path)?;
-
-    // Determine output path
-    let output_path = cli.output.unwrap_or_else(|| {
+    // Determine output path; stdin input goes to stdout unless -o says otherwise
+    let output_path = cli.output.clone().unwrap_or_else(|| {
+        if from_stdin {
+            return PathBuf::from("-");
+        }
         let mut path = input_path.clone();
         path.set_extension("md");
         path
     });
 
-    // Write output
-    std::fs::write(&output_path, markdown)?;
+    // Setup configuration
+    let loaded = cli.pipeline.load_config()?;
+    eprintln!("📄 Input: {}", input_path.display());
+    print_config_sources(&loaded);
+    let converter = cli.pipeline.converter(&loaded)?;
+    // Figures get a folder per document, as in batch mode, so documents converted next to
+    // each other do not share page-N-fig-M.png names
+    let assets_dir = cli.assets_dir.clone().unwrap_or_else(|| {
+        let named = if is_stdout(&output_path) {
+            &input_path
+        } else {
+            &output_path
+        };
+        let stem = named
+            .file_stem()
+            .filter(|_| !is_stdin(named))
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "stdin".to_string());
+        output_path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(&converter.config().assets_dir)
+            .join(stem)
+    });
+    let options = ConvertOptions {
+        pages: Some(cli.pages.clone()),
+        output: Some(output_path.clone()),
+        stdout: is_stdout(&output_path),
+        assets_dir: Some(assets_dir),
+        resume: cli.pipeline.resume,
+        json: cli.json.clone(),
+    };
+    let converted = if from_stdin {
+        read_stdin()
+            .and_then(|bytes| converter.convert_bytes_with(bytes, Path::new("stdin"), &options))
+    } else {
+        converter.convert(&input_path, &options)
+    };
 
     let duration = start.elapsed();
-    println!("\n✨ Success!");
-    println!("📄 Output: {}", output_path.display());
-    println!("⏱️  Time: {:.2}s", duration.as_secs_f64());
-    println!("🚀 Speed: {:.2} pages/sec", page_range.len() as f64 / duration.as_secs_f64());
+    if let Some(report_path) = &cli.pipeline.report {
+        let document = match &converted {
+            Ok(document) => document.report.clone(),
+            Err(e) => DocumentReport::not_converted(
+                &input_path,
+                Status::Failed,
+                Some(format!("{:#}", e)),
+                duration,
+            ),
+        };
+        RunReport::new(vec![document], duration).write(report_path)?;
+        eprintln!("📊 Report: {}", report_path.display());
+    }
+    let document = converted?;
+    print_failures(&document.failures, cli.pipeline.progress);
+    let pages = document.selected;
+    let interrupted = document.interrupted.len();
+    let failed = pages - document.pages.len() - interrupted;
+
+    if interrupted > 0 {
+        eprintln!("\n⏸️  Stopped with {} page(s) left", interrupted);
+    } else if failed == 0 {
+        eprintln!("\n✨ Success!");
+    } else {
+        eprintln!("\n⚠️  Finished with {} failed page(s)", failed);
+    }
+    eprintln!("📄 Output: {}", output_path.display());
+    eprintln!("⏱️  Time: {:.2}s", duration.as_secs_f64());
+    eprintln!("🚀 Speed: {:.2} pages/sec", pages as f64 / duration.as_secs_f64());
+
+    if interrupted > 0 {
+        return Err(OcrError::Interrupted(format!(
+            "{} of {} pages not converted; partial output written",
+            interrupted, pages
+        ))
+        .into());
+    }
+    if document.pages.is_empty() {
+        return Err(OcrError::TotalFailure(format!("all {} pages failed", pages)).into());
+    }
+    if failed > 0 {
+        return Err(
+            OcrError::PartialFailure(format!("{} of {} pages failed", failed, pages)).into(),
+        );
+    }
 
-    wait_for_enter();
+    if cli.pipeline.interactive() {
+        wait_for_enter();
+    }
     Ok(())
 }
 
-fn parse_page_range(range_str: &str, total_pages: usize) -> Result<Vec<usize>> {
-    if range_str == "all" {
-        return Ok((1..=total_pages).collect());
-    }
+/// `-` as input reads a PDF from stdin.
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
 
-    let parts: Vec<&str> = range_str.split('-').collect();
-    match parts.len() {
-        1 => {
-            let page: usize = parts[0].parse()?;
-            if page < 1 || page > total_pages {
-                anyhow::bail!("Page {} out of range (1-{})", page, total_pages);
-            }
-            Ok(vec![page])
-        }
-        2 => {
-            let start: usize = parts[0].parse()?;
-            let end: usize = parts[1].parse()?;
-            if start < 1 || end > total_pages || start > end {
-                anyhow::bail!("Invalid page range: {}", range_str);
-            }
-            Ok((start..=end).collect())
-        }
-        _ => anyhow::bail!("Invalid page range format: {}", range_str),
+/// `-` as output writes the Markdown to stdout.
+pub(crate) fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+fn read_stdin() -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    io::stdin()
+        .lock()
+        .read_to_end(&mut bytes)
+        .context("Failed to read PDF from stdin")?;
+    Ok(bytes)
+}
+
+/// List failed pages; JSON consumers already received a page_error event for each.
+fn print_failures(failures: &[PageFailure], mode: ProgressMode) {
+    if failures.is_empty() || mode == ProgressMode::Json {
+        return;
+    }
+    eprintln!("⚠️  Errors occurred on {} pages:", failures.len());
+    for failure in failures {
+        eprintln!("  Page {} ({}): {}", failure.page, failure.stage, failure.message);
     }
 }
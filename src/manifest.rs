@@ -0,0 +1,341 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::pdf_processor::PageResult;
+use crate::progress::{Event, ProgressHandler};
+
+/// One line of a run manifest.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+enum Entry {
+    /// First line: what the pages were converted from and with
+    Run {
+        tool: String,
+        input: String,
+        input_fingerprint: String,
+        config_fingerprint: String,
+    },
+    /// A page that converted
+    Page { result: PageResult },
+}
+
+/// JSON-lines file next to the output that records every converted page as it finishes,
+/// so an interrupted or partly failed run can be resumed with `--resume`.
+///
+/// It is removed once the Markdown is complete.
+pub struct Manifest {
+    path: PathBuf,
+    file: File,
+}
+
+impl Manifest {
+    /// `paper.md` → `paper.manifest.jsonl`
+    pub fn path_for(output: &Path) -> PathBuf {
+        output.with_extension("manifest.jsonl")
+    }
+
+    /// Start recording a run, replacing any previous manifest.
+    ///
+    /// With `resume`, pages recorded by an earlier run of the same input and settings are
+    /// returned and carried over; a manifest for anything else is ignored with a warning.
+    pub fn start(
+        output: &Path,
+        input: &Path,
+        config_fingerprint: &str,
+        resume: bool,
+        progress: &dyn ProgressHandler,
+    ) -> Result<(Self, Vec<PageResult>)> {
+        let path = Self::path_for(output);
+        let header = Entry::Run {
+            tool: format!("RustOCR2md v{}", env!("CARGO_PKG_VERSION")),
+            input: input.display().to_string(),
+            input_fingerprint: input_fingerprint(input),
+            config_fingerprint: config_fingerprint.to_string(),
+        };
+
+        let previous = if resume {
+            read_pages(&path, &header, progress)?
+        } else {
+            Vec::new()
+        };
+
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create manifest {}", path.display()))?;
+        let mut manifest = Self { path, file };
+        manifest.append(&header)?;
+        for result in &previous {
+            manifest.record(result)?;
+        }
+        Ok((manifest, previous))
+    }
+
+    pub fn record(&mut self, result: &PageResult) -> Result<()> {
+        self.append(&Entry::Page {
+            result: result.clone(),
+        })
+    }
+
+    /// Delete the manifest; nothing is left to resume.
+    pub fn finish(self) -> Result<()> {
+        drop(self.file);
+        std::fs::remove_file(&self.path)
+            .with_context(|| format!("Failed to remove manifest {}", self.path.display()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn append(&mut self, entry: &Entry) -> Result<()> {
+        // One flushed line per page, so a crash loses at most the page being written
+        let line = serde_json::to_string(entry)?;
+        writeln!(self.file, "{}", line)
+            .and_then(|_| self.file.flush())
+            .with_context(|| format!("Failed to write manifest {}", self.path.display()))
+    }
+}
+
+/// Pages from an existing manifest whose header matches `expected`.
+fn read_pages(
+    path: &Path,
+    expected: &Entry,
+    progress: &dyn ProgressHandler,
+) -> Result<Vec<PageResult>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            progress.on_event(&Event::Info {
+                message: format!("ℹ️  Nothing to resume: {} not found", path.display()),
+            });
+            return Ok(Vec::new());
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read manifest {}", path.display()))
+        }
+    };
+
+    let mut lines = BufReader::new(file).lines();
+    let header = lines
+        .next()
+        .transpose()?
+        .and_then(|line| serde_json::from_str::<Entry>(&line).ok());
+    let matches = match (&header, expected) {
+        (
+            Some(Entry::Run {
+                input,
+                input_fingerprint,
+                config_fingerprint,
+                ..
+            }),
+            Entry::Run {
+                input: expected_input,
+                input_fingerprint: expected_input_fingerprint,
+                config_fingerprint: expected_config_fingerprint,
+                ..
+            },
+        ) => {
+            input == expected_input
+                && input_fingerprint == expected_input_fingerprint
+                && config_fingerprint == expected_config_fingerprint
+        }
+        _ => false,
+    };
+    if !matches {
+        progress.on_event(&Event::Info {
+            message: format!(
+                "⚠️  {} is for a different input or different settings, starting over",
+                path.display()
+            ),
+        });
+        return Ok(Vec::new());
+    }
+
+    // A run killed mid-write can leave a truncated last line; it is simply redone
+    let mut pages: Vec<PageResult> = Vec::new();
+    for line in lines.map_while(|line| line.ok()) {
+        if let Ok(Entry::Page { result }) = serde_json::from_str(&line) {
+            pages.retain(|p| p.page_num != result.page_num);
+            pages.push(result);
+        }
+    }
+    pages.sort_by_key(|p| p.page_num);
+    Ok(pages)
+}
+
+/// Size and modification time; changes whenever the input is replaced.
+pub fn input_fingerprint(input: &Path) -> String {
+    match std::fs::metadata(input) {
+        Ok(meta) => {
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            format!("{}:{}", meta.len(), modified)
+        }
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoProgress;
+    use std::sync::Mutex;
+
+    /// An input file and an output path in a fresh temporary directory, removed on drop.
+    struct TempRun {
+        dir: PathBuf,
+        input: PathBuf,
+        output: PathBuf,
+    }
+
+    impl TempRun {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "rust-ocr2md-manifest-test-{}-{name}",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            let input = dir.join("paper.pdf");
+            std::fs::write(&input, b"%PDF-1.7").unwrap();
+            Self {
+                output: dir.join("paper.md"),
+                input,
+                dir,
+            }
+        }
+
+        fn start(&self, config: &str, resume: bool) -> (Manifest, Vec<PageResult>) {
+            Manifest::start(&self.output, &self.input, config, resume, &NoProgress).unwrap()
+        }
+    }
+
+    impl Drop for TempRun {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn page(page_num: usize) -> PageResult {
+        PageResult {
+            page_num,
+            blocks: Vec::new(),
+            detected_language: Some(format!("lang-{}", page_num)),
+            image_width: 100,
+            image_height: 200,
+            links: Vec::new(),
+            annotations: Vec::new(),
+        }
+    }
+
+    fn page_nums(pages: &[PageResult]) -> Vec<usize> {
+        pages.iter().map(|p| p.page_num).collect()
+    }
+
+    #[test]
+    fn resumes_recorded_pages_in_order() {
+        let run = TempRun::new("resume");
+        let (mut manifest, previous) = run.start("config", false);
+        assert!(previous.is_empty());
+        assert_eq!(manifest.path(), run.dir.join("paper.manifest.jsonl"));
+        manifest.record(&page(3)).unwrap();
+        manifest.record(&page(1)).unwrap();
+        drop(manifest);
+
+        let (manifest, previous) = run.start("config", true);
+        assert_eq!(page_nums(&previous), vec![1, 3]);
+        assert_eq!(previous[1].detected_language.as_deref(), Some("lang-3"));
+        assert_eq!(previous[1].image_height, 200);
+        drop(manifest);
+
+        // Carried-over pages are written again, so a second resume still finds them
+        let (_, previous) = run.start("config", true);
+        assert_eq!(page_nums(&previous), vec![1, 3]);
+    }
+
+    #[test]
+    fn keeps_the_last_record_of_a_page() {
+        let run = TempRun::new("rerecord");
+        let (mut manifest, _) = run.start("config", false);
+        manifest.record(&page(1)).unwrap();
+        let mut again = page(1);
+        again.detected_language = Some("eng".to_string());
+        manifest.record(&again).unwrap();
+        drop(manifest);
+
+        let (_, previous) = run.start("config", true);
+        assert_eq!(page_nums(&previous), vec![1]);
+        assert_eq!(previous[0].detected_language.as_deref(), Some("eng"));
+    }
+
+    #[test]
+    fn skips_a_truncated_last_line() {
+        let run = TempRun::new("truncated");
+        let (mut manifest, _) = run.start("config", false);
+        manifest.record(&page(1)).unwrap();
+        let path = manifest.path().to_path_buf();
+        drop(manifest);
+        let mut file = File::options().append(true).open(&path).unwrap();
+        write!(file, "{{\"entry\":\"page\",\"result\":{{\"page_num\":2,").unwrap();
+        drop(file);
+
+        let (_, previous) = run.start("config", true);
+        assert_eq!(page_nums(&previous), vec![1]);
+    }
+
+    #[test]
+    fn starts_over_for_different_settings() {
+        let run = TempRun::new("settings");
+        let (mut manifest, _) = run.start("config", false);
+        manifest.record(&page(1)).unwrap();
+        drop(manifest);
+
+        let messages = Mutex::new(Vec::new());
+        let progress = |event: &Event| {
+            if let Event::Info { message } = event {
+                messages.lock().unwrap().push(message.clone());
+            }
+        };
+        let (_, previous) =
+            Manifest::start(&run.output, &run.input, "other", true, &progress).unwrap();
+        assert!(previous.is_empty());
+        let messages = messages.into_inner().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("different input or different settings"));
+
+        // The new run replaced the old manifest
+        let (_, previous) = run.start("config", true);
+        assert!(previous.is_empty());
+    }
+
+    #[test]
+    fn starts_over_without_resume() {
+        let run = TempRun::new("no-resume");
+        let (mut manifest, _) = run.start("config", false);
+        manifest.record(&page(1)).unwrap();
+        drop(manifest);
+
+        let (_, previous) = run.start("config", false);
+        assert!(previous.is_empty());
+    }
+
+    #[test]
+    fn finish_removes_the_manifest() {
+        let run = TempRun::new("finish");
+        let (manifest, _) = run.start("config", false);
+        let path = manifest.path().to_path_buf();
+        assert!(path.is_file());
+        manifest.finish().unwrap();
+        assert!(!path.exists());
+
+        let (_, previous) = run.start("config", true);
+        assert!(previous.is_empty());
+    }
+}
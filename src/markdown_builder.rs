@@ -1,63 +1,451 @@
 use anyhow::Result;
 use chrono::Local;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
 use std::path::Path;
 
-use crate::ocr_engine::BlockType;
-use crate::pdf_processor::PageResult;
+use crate::annotations::{self, LinkTarget, PageLink};
+use crate::config::Config;
+use crate::error::PageFailure;
+use crate::ocr_engine::{BlockType, OcrBlock};
+use crate::outline::{AnchorGenerator, OutlineEntry};
+use crate::pdf_processor::{DocumentInfo, PageResult};
 
 pub struct MarkdownBuilder {
     include_metadata: bool,
+    assets_link: String,
+    outline: Vec<OutlineEntry>,
+    include_toc: bool,
+    info: DocumentInfo,
+    page_labels: Vec<Option<String>>,
+    failures: Vec<PageFailure>,
+    missing: Vec<usize>,
+    join_lines: bool,
+    dehyphenate: bool,
+    merge_paragraphs: bool,
 }
 
 impl MarkdownBuilder {
     pub fn new() -> Self {
         Self {
             include_metadata: true,
+            assets_link: "assets".to_string(),
+            outline: Vec::new(),
+            include_toc: false,
+            info: DocumentInfo::default(),
+            page_labels: Vec::new(),
+            failures: Vec::new(),
+            missing: Vec::new(),
+            join_lines: false,
+            dehyphenate: false,
+            merge_paragraphs: false,
         }
     }
 
+    /// A builder with the Markdown options from `config`: table of contents, metadata
+    /// header and line and paragraph handling.
+    pub fn from_config(config: &Config) -> Self {
+        Self::new()
+            .with_toc(config.toc)
+            .with_metadata(config.metadata)
+            .with_join_lines(config.join_lines)
+            .with_dehyphenate(config.dehyphenate)
+            .with_merge_paragraphs(config.merge_paragraphs)
+    }
+
+    /// Start with the document title and a metadata list.
+    pub fn with_metadata(mut self, include_metadata: bool) -> Self {
+        self.include_metadata = include_metadata;
+        self
+    }
+
+    /// Write each text block on one line instead of keeping the OCR line breaks.
+    pub fn with_join_lines(mut self, join_lines: bool) -> Self {
+        self.join_lines = join_lines;
+        self
+    }
+
+    /// Rejoin words hyphenated across a line break, e.g. "recog-" + "nition".
+    pub fn with_dehyphenate(mut self, dehyphenate: bool) -> Self {
+        self.dehyphenate = dehyphenate;
+        self
+    }
+
+    /// Join a text block to the previous one when it continues the same sentence.
+    pub fn with_merge_paragraphs(mut self, merge_paragraphs: bool) -> Self {
+        self.merge_paragraphs = merge_paragraphs;
+        self
+    }
+
+    /// Include fields from the PDF info dictionary in the metadata header.
+    pub fn with_document_info(mut self, info: DocumentInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Page labels indexed by page number - 1, used in page markers.
+    pub fn with_page_labels(mut self, page_labels: Vec<Option<String>>) -> Self {
+        self.page_labels = page_labels;
+        self
+    }
+
+    /// Use the PDF outline as the heading hierarchy.
+    pub fn with_outline(mut self, outline: Vec<OutlineEntry>) -> Self {
+        self.outline = outline;
+        self
+    }
+
+    /// Generate a linked table of contents from the outline.
+    pub fn with_toc(mut self, include_toc: bool) -> Self {
+        self.include_toc = include_toc;
+        self
+    }
+
+    /// Path prefix for figure links, relative to the Markdown file.
+    pub fn with_assets_link(mut self, assets_link: impl Into<String>) -> Self {
+        self.assets_link = assets_link.into();
+        self
+    }
+
+    /// Leave a placeholder comment where each failed page would have been.
+    pub fn with_failed_pages(mut self, failures: Vec<PageFailure>) -> Self {
+        self.failures = failures;
+        self
+    }
+
+    /// Mark the document as incomplete because these pages were never converted.
+    pub fn with_missing_pages(mut self, missing: Vec<usize>) -> Self {
+        self.missing = missing;
+        self
+    }
+
     pub fn build(&self, results: &[PageResult], source_path: &Path) -> Result<String> {
+        let pages: Vec<usize> = results.iter().map(|r| r.page_num).collect();
+        // Pages that internal links point at get an explicit anchor
+        let link_targets: HashSet<usize> = results
+            .iter()
+            .flat_map(|r| &r.links)
+            .filter_map(|link| match link.target {
+                LinkTarget::Page(page) => Some(page),
+                LinkTarget::Uri(_) => None,
+            })
+            .collect();
+
+        let (mut markdown, mut sections) =
+            self.start(source_path, &pages, Some(link_targets), true);
+        let mut failures = self.failures.iter().peekable();
+
+        // Add content from each page
+        for result in results {
+            while let Some(failure) = failures.next_if(|f| f.page < result.page_num) {
+                markdown.push_str(&placeholder(failure));
+            }
+            markdown.push_str(&self.page_section(result, &mut sections));
+        }
+        for failure in failures {
+            markdown.push_str(&placeholder(failure));
+        }
+
+        Ok(markdown)
+    }
+
+    /// Start writing the document to `writer` page by page; see [`MarkdownStream`].
+    ///
+    /// `pages` are the pages that will be pushed, in order.
+    pub fn stream<'a>(
+        &'a self,
+        mut writer: Box<dyn Write + 'a>,
+        source_path: &Path,
+        pages: &[usize],
+        placeholders: bool,
+    ) -> Result<MarkdownStream<'a>> {
+        // Later pages' links are not known yet, so every page gets an anchor
+        let (header, sections) = self.start(source_path, pages, None, false);
+        writer.write_all(header.as_bytes())?;
+        writer.flush()?;
+        Ok(MarkdownStream {
+            builder: self,
+            writer,
+            sections,
+            pending: pages.iter().map(|&page| (page, None)).collect(),
+            placeholders,
+        })
+    }
+
+    /// Metadata header, incomplete marker and table of contents, plus the state the page
+    /// sections need. Headings are kept for outline entries on `pages`.
+    fn start(
+        &self,
+        source_path: &Path,
+        pages: &[usize],
+        link_targets: Option<HashSet<usize>>,
+        with_missing: bool,
+    ) -> (String, Sections<'_>) {
         let mut markdown = String::new();
 
         // Add metadata header
         if self.include_metadata {
-            markdown.push_str(&self.generate_metadata(source_path, results));
+            markdown.push_str(&self.generate_metadata(source_path, pages.len()));
             markdown.push_str("\n---\n\n");
         }
 
-        // Add content from each page
-        for result in results {
-            markdown.push_str(&format!("## Page {}\n\n", result.page_num));
-
-            for block in &result.blocks {
-                let cleaned_text = self.clean_ocr_text(&block.text);
-                match block.block_type {
-                    BlockType::Formula => {
-                        markdown.push_str(&cleaned_text);
-                        markdown.push_str("\n\n");
-                    }
-                    _ => {
-                        markdown.push_str(&cleaned_text);
-                        markdown.push_str("\n\n");
+        if with_missing && !self.missing.is_empty() {
+            markdown.push_str(&incomplete_marker(&self.missing));
+        }
+
+        // Only keep outline entries that point at pages we actually have
+        let mut anchors = AnchorGenerator::new();
+        if self.include_metadata {
+            anchors.anchor(self.info.title.as_deref().unwrap_or("Document OCR Result"));
+        }
+        if self.include_toc {
+            anchors.anchor("Table of Contents");
+        }
+        let headings: Vec<(&OutlineEntry, String)> = self
+            .outline
+            .iter()
+            .filter(|entry| pages.contains(&entry.page_num))
+            .map(|entry| (entry, anchors.anchor(&entry.title)))
+            .collect();
+
+        if self.include_toc && !headings.is_empty() {
+            markdown.push_str(&self.generate_toc(&headings));
+            markdown.push('\n');
+        }
+
+        let sections = Sections {
+            headings: headings.into_iter().map(|(entry, _)| entry).collect(),
+            link_targets,
+            pages: pages.iter().copied().collect(),
+            footnote_count: 0,
+        };
+        (markdown, sections)
+    }
+
+    fn page_section(&self, result: &PageResult, sections: &mut Sections) -> String {
+        let mut markdown = String::new();
+        let mut page_headings = sections
+            .headings
+            .iter()
+            .filter(|entry| entry.page_num == result.page_num)
+            .copied()
+            .peekable();
+
+        let page_name = self.page_name(result.page_num);
+        let anchored = sections
+            .link_targets
+            .as_ref()
+            .is_none_or(|targets| targets.contains(&result.page_num));
+        if anchored {
+            markdown.push_str(&format!("<a id=\"page-{}\"></a>\n\n", result.page_num));
+        }
+        if sections.headings.is_empty() {
+            markdown.push_str(&format!("## Page {}\n\n", page_name));
+        } else {
+            // Outline headings carry the structure; keep page breaks as comments
+            markdown.push_str(&format!("<!-- Page {} -->\n\n", page_name));
+        }
+
+        // A link to a page left out of the output would point nowhere; its text stays plain
+        let links: Vec<PageLink> = result
+            .links
+            .iter()
+            .filter(|link| match link.target {
+                LinkTarget::Page(page) => sections.pages.contains(&page),
+                LinkTarget::Uri(_) => true,
+            })
+            .cloned()
+            .collect();
+        let owners = annotations::assign_annotations(&result.blocks, &result.annotations);
+        let mut notes = Vec::new();
+
+        let page_height = result.image_height.max(1) as f32;
+        let mut last_heading: Option<&str> = None;
+        // The last block written was text that stopped mid-sentence
+        let mut open_paragraph = false;
+        for (idx, block) in result.blocks.iter().enumerate() {
+            // Without a position (e.g. Tesseract's single block) the headings go first
+            let top = block
+                .bbox
+                .as_ref()
+                .map(|b| b.y as f32 / page_height)
+                .unwrap_or(f32::INFINITY);
+            while let Some(entry) = page_headings.next_if(|e| e.position.unwrap_or(0.0) <= top) {
+                markdown.push_str(&self.heading(entry));
+                last_heading = Some(&entry.title);
+                open_paragraph = false;
+            }
+
+            let note_ids: Vec<usize> = owners
+                .iter()
+                .enumerate()
+                .filter(|(_, owner)| **owner == Some(idx))
+                .map(|(annotation_idx, _)| {
+                    sections.footnote_count += 1;
+                    notes.push((
+                        sections.footnote_count,
+                        annotations::describe(&result.annotations[annotation_idx]),
+                    ));
+                    sections.footnote_count
+                })
+                .collect();
+
+            // The bookmark title usually repeats the printed heading
+            if note_ids.is_empty()
+                && last_heading.is_some_and(|title| same_text(title, &block.text))
+            {
+                last_heading = None;
+                continue;
+            }
+            last_heading = None;
+
+            let continues = open_paragraph
+                && block.block_type == BlockType::Text
+                && starts_mid_sentence(&block.text);
+            if continues {
+                markdown.truncate(markdown.trim_end().len());
+            }
+            self.push_block(&mut markdown, block, &links, &note_ids, continues);
+            open_paragraph = self.merge_paragraphs
+                && block.block_type == BlockType::Text
+                && note_ids.is_empty()
+                && !ends_sentence(&markdown);
+        }
+        for entry in page_headings {
+            markdown.push_str(&self.heading(entry));
+        }
+
+        for (id, note) in &notes {
+            markdown.push_str(&format!("[^note-{}]: {}\n", id, note));
+        }
+        if !notes.is_empty() {
+            markdown.push('\n');
+        }
+        markdown
+    }
+
+    fn push_block(
+        &self,
+        markdown: &mut String,
+        block: &OcrBlock,
+        links: &[PageLink],
+        note_ids: &[usize],
+        continues: bool,
+    ) {
+        let cleaned_text = self.clean_ocr_text(&block.text);
+        let note_refs: String = note_ids
+            .iter()
+            .map(|id| format!("[^note-{}]", id))
+            .collect();
+        match block.block_type {
+            BlockType::Formula => {
+                markdown.push_str(&cleaned_text);
+                markdown.push_str(&note_refs);
+                markdown.push_str("\n\n");
+            }
+            BlockType::Figure => {
+                if let Some(name) = &block.image {
+                    markdown.push_str(&self.figure_link(&cleaned_text, name));
+                    markdown.push_str(&note_refs);
+                    markdown.push_str("\n\n");
+                }
+            }
+            _ => {
+                let cleaned_text = self.reflow(&cleaned_text);
+                let text = match &block.bbox {
+                    Some(bbox) if !links.is_empty() => {
+                        annotations::apply_links(&cleaned_text, bbox, links, |page| {
+                            format!("#page-{}", page)
+                        })
                     }
+                    _ => cleaned_text,
+                };
+                if continues {
+                    self.line_break(markdown, &text);
                 }
+                markdown.push_str(&text);
+                markdown.push_str(&note_refs);
+                markdown.push_str("\n\n");
             }
         }
+    }
 
-        Ok(markdown)
+    fn heading(&self, entry: &OutlineEntry) -> String {
+        // Level 0 maps to "##" because "#" is the document title
+        let hashes = "#".repeat((entry.level + 2).min(6));
+        format!("{} {}\n\n", hashes, entry.title)
+    }
+
+    fn generate_toc(&self, headings: &[(&OutlineEntry, String)]) -> String {
+        let mut toc = String::from("## Table of Contents\n\n");
+        for (entry, anchor) in headings {
+            toc.push_str(&format!(
+                "{}- [{}](#{})\n",
+                "  ".repeat(entry.level),
+                entry.title.replace(['[', ']'], ""),
+                anchor
+            ));
+        }
+        toc
     }
 
-    fn generate_metadata(&self, source_path: &Path, results: &[PageResult]) -> String {
-        format!(
-            "# Document OCR Result\n\n\
-            - **Source**: {}\n\
+    fn generate_metadata(&self, source_path: &Path, page_count: usize) -> String {
+        let mut metadata = format!(
+            "# {}\n\n",
+            self.info.title.as_deref().unwrap_or("Document OCR Result")
+        );
+
+        let fields = [
+            ("Author", &self.info.author),
+            ("Subject", &self.info.subject),
+            ("Keywords", &self.info.keywords),
+            ("Created", &self.info.created),
+            ("Modified", &self.info.modified),
+            ("Creator", &self.info.creator),
+            ("Producer", &self.info.producer),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                metadata.push_str(&format!("- **{}**: {}\n", name, value));
+            }
+        }
+
+        metadata.push_str(&format!(
+            "- **Source**: {}\n\
             - **Processed**: {}\n\
             - **Total Pages**: {}\n\
             - **Tool**: RustOCR2md\n",
             source_path.display(),
             Local::now().format("%Y-%m-%d %H:%M:%S"),
-            results.len()
-        )
+            page_count
+        ));
+        if !self.failures.is_empty() {
+            let pages: Vec<String> = self.failures.iter().map(|f| f.page.to_string()).collect();
+            metadata.push_str(&format!("- **Failed Pages**: {}\n", pages.join(", ")));
+        }
+        metadata
+    }
+
+    /// "12", or "iv (5)" when the page carries a label that differs from its number.
+    fn page_name(&self, page_num: usize) -> String {
+        let label = page_num
+            .checked_sub(1)
+            .and_then(|idx| self.page_labels.get(idx))
+            .and_then(|l| l.as_deref());
+        match label {
+            Some(label) if label != page_num.to_string() => format!("{} ({})", label, page_num),
+            _ => page_num.to_string(),
+        }
+    }
+
+    fn figure_link(&self, caption: &str, name: &str) -> String {
+        let alt = caption.replace('\n', " ").replace(['[', ']'], "");
+        let link = if self.assets_link.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.assets_link.trim_end_matches('/'), name)
+        };
+        format!("![{}]({})", alt, link.replace(' ', "%20"))
     }
 
     fn clean_ocr_text(&self, text: &str) -> String {
@@ -67,6 +455,116 @@ impl MarkdownBuilder {
             .collect::<Vec<_>>()
             .join("\n")
     }
+
+    /// Apply the line options to the cleaned text of a text block.
+    fn reflow(&self, text: &str) -> String {
+        let mut reflowed = String::new();
+        for line in text.lines() {
+            if !reflowed.is_empty() {
+                self.line_break(&mut reflowed, line);
+            }
+            reflowed.push_str(line);
+        }
+        reflowed
+    }
+
+    /// Separate `text` from the `next` line about to be appended to it.
+    fn line_break(&self, text: &mut String, next: &str) {
+        if self.dehyphenate && is_hyphen_break(text, next) {
+            text.pop();
+        } else if !self.join_lines {
+            text.push('\n');
+        } else if !(text.chars().last().is_some_and(is_cjk)
+            && next.chars().next().is_some_and(is_cjk))
+        {
+            // CJK text has no spaces between words
+            text.push(' ');
+        }
+    }
+}
+
+/// Document-wide state carried from one page section to the next.
+struct Sections<'a> {
+    /// Outline entries on the pages being written
+    headings: Vec<&'a OutlineEntry>,
+    /// Pages that need an anchor; `None` anchors every page
+    link_targets: Option<HashSet<usize>>,
+    /// Pages in the output, the only ones internal links can point at
+    pages: HashSet<usize>,
+    footnote_count: usize,
+}
+
+/// How the conversion feeding a [`MarkdownStream`] ended.
+pub enum StreamEnd<'e> {
+    /// Processing ran its course; pages that never arrived were interrupted
+    Completed,
+    /// `--on-error abort` stopped at this failed page
+    Aborted(&'e PageFailure),
+    /// Processing stopped with an error that belongs to no single page
+    Failed(&'e anyhow::Error),
+}
+
+/// A page waiting for its turn in a [`MarkdownStream`].
+enum Ready {
+    Page(PageResult),
+    Failed(PageFailure),
+}
+
+/// Markdown written while OCR is still running: each page section goes out as soon as
+/// every page before it is done, so the output can be read or piped early.
+pub struct MarkdownStream<'a> {
+    builder: &'a MarkdownBuilder,
+    writer: Box<dyn Write + 'a>,
+    sections: Sections<'a>,
+    /// Pages not written yet, in order, with their outcome once known
+    pending: BTreeMap<usize, Option<Ready>>,
+    placeholders: bool,
+}
+
+impl MarkdownStream<'_> {
+    pub fn page(&mut self, result: &PageResult) -> Result<()> {
+        self.ready(result.page_num, Ready::Page(result.clone()))
+    }
+
+    pub fn failed(&mut self, failure: &PageFailure) -> Result<()> {
+        self.ready(failure.page, Ready::Failed(failure.clone()))
+    }
+
+    /// Close the document; pages that never arrived are listed as missing, with `end`
+    /// saying why.
+    pub fn finish(mut self, end: StreamEnd) -> Result<()> {
+        let missing: Vec<usize> = self.pending.keys().copied().collect();
+        if !missing.is_empty() {
+            let marker = match end {
+                StreamEnd::Completed => incomplete_marker(&missing),
+                StreamEnd::Aborted(failure) => aborted_marker(failure, &missing),
+                StreamEnd::Failed(error) => failed_marker(error, &missing),
+            };
+            self.writer.write_all(marker.as_bytes())?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn ready(&mut self, page: usize, outcome: Ready) -> Result<()> {
+        if let Some(slot) = self.pending.get_mut(&page) {
+            *slot = Some(outcome);
+        }
+        while let Some(entry) = self.pending.first_entry() {
+            let Some(outcome) = entry.get() else {
+                break;
+            };
+            let section = match outcome {
+                Ready::Page(result) => self.builder.page_section(result, &mut self.sections),
+                Ready::Failed(failure) if self.placeholders => placeholder(failure),
+                Ready::Failed(_) => String::new(),
+            };
+            entry.remove();
+            self.writer.write_all(section.as_bytes())?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
 }
 
 impl Default for MarkdownBuilder {
@@ -74,3 +572,240 @@ impl Default for MarkdownBuilder {
         Self::new()
     }
 }
+
+/// "recog-" followed by "nition", but not "well-" followed by "Known" or a lone dash.
+fn is_hyphen_break(text: &str, next: &str) -> bool {
+    let mut tail = text.chars().rev();
+    tail.next() == Some('-')
+        && tail.next().is_some_and(char::is_alphabetic)
+        && next.chars().next().is_some_and(char::is_lowercase)
+}
+
+fn ends_sentence(markdown: &str) -> bool {
+    markdown
+        .trim_end()
+        .chars()
+        .last()
+        .is_none_or(|c| ".!?:;。！？：；…".contains(c))
+}
+
+fn starts_mid_sentence(text: &str) -> bool {
+    text.trim_start()
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_lowercase() || is_cjk(c))
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF
+    )
+}
+
+fn same_text(a: &str, b: &str) -> bool {
+    let normalize = |s: &str| {
+        s.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// `<!-- page 17 failed: ... -->`, with the message kept on one line and free of `--`.
+fn placeholder(failure: &PageFailure) -> String {
+    let mut message = one_line(&failure.message);
+    while message.contains("--") {
+        message = message.replace("--", "- -");
+    }
+    format!(
+        "<!-- page {} failed: {}: {} -->\n\n",
+        failure.page, failure.stage, message
+    )
+}
+
+fn incomplete_marker(missing: &[usize]) -> String {
+    format!(
+        "<!-- incomplete: pages {} not converted -->\n\n\
+         > **Incomplete**: the conversion was interrupted before pages {} were converted. \
+         Run it again with `--resume` to finish.\n\n",
+        format_pages(missing),
+        format_pages(missing)
+    )
+}
+
+fn aborted_marker(failure: &PageFailure, missing: &[usize]) -> String {
+    format!(
+        "<!-- incomplete: aborted at page {} -->\n\n\
+         > **Incomplete**: the conversion was aborted at page {}, which failed during {}: {}. \
+         Pages {} were not converted.\n\n",
+        failure.page,
+        failure.page,
+        failure.stage,
+        one_line(&failure.message),
+        format_pages(missing)
+    )
+}
+
+fn failed_marker(error: &anyhow::Error, missing: &[usize]) -> String {
+    format!(
+        "<!-- incomplete: conversion failed -->\n\n\
+         > **Incomplete**: the conversion failed before pages {} were converted: {}\n\n",
+        format_pages(missing),
+        one_line(&error.to_string())
+    )
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `[3, 4, 5, 9]` → `3-5, 9`
+fn format_pages(pages: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &page in pages {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == page => *end = page,
+            _ => ranges.push((page, page)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Stage;
+    use crate::ocr_engine::BoundingBox;
+
+    #[test]
+    fn format_pages_joins_runs() {
+        assert_eq!(format_pages(&[]), "");
+        assert_eq!(format_pages(&[4]), "4");
+        assert_eq!(format_pages(&[1, 2, 3, 5, 7, 8]), "1-3, 5, 7-8");
+    }
+
+    fn linked_page(page_num: usize, text: &str, target: usize) -> PageResult {
+        let bbox = BoundingBox {
+            x: 0,
+            y: 0,
+            width: 100,
+            height: 20,
+        };
+        PageResult {
+            page_num,
+            blocks: vec![OcrBlock {
+                text: text.to_string(),
+                confidence: 1.0,
+                bbox: Some(bbox.clone()),
+                block_type: BlockType::Text,
+                language: None,
+                image: None,
+            }],
+            detected_language: None,
+            image_width: 100,
+            image_height: 100,
+            links: vec![PageLink {
+                bbox,
+                target: LinkTarget::Page(target),
+            }],
+            annotations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn internal_links_only_to_pages_in_the_output() {
+        let results = [linked_page(1, "see two", 2), linked_page(2, "see five", 5)];
+        let markdown = MarkdownBuilder::new()
+            .with_metadata(false)
+            .build(&results, Path::new("doc.pdf"))
+            .unwrap();
+        assert!(markdown.contains("[see two](#page-2)"));
+        assert!(markdown.contains("<a id=\"page-2\"></a>"));
+        assert!(markdown.contains("see five"));
+        assert!(!markdown.contains("#page-5"));
+    }
+
+    /// Stream pages 1-4, deliver only page 1 and finish with `end`.
+    fn stream_ending(end: StreamEnd) -> String {
+        let builder = MarkdownBuilder::new().with_metadata(false);
+        let mut output = Vec::new();
+        let mut stream = builder
+            .stream(
+                Box::new(&mut output),
+                Path::new("doc.pdf"),
+                &[1, 2, 3, 4],
+                true,
+            )
+            .unwrap();
+        stream.page(&linked_page(1, "first page", 1)).unwrap();
+        stream.finish(end).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn interrupted_stream_suggests_resume() {
+        let markdown = stream_ending(StreamEnd::Completed);
+        assert!(markdown.contains("first page"));
+        assert!(markdown.contains("<!-- incomplete: pages 2-4 not converted -->"));
+        assert!(markdown.contains("--resume"));
+    }
+
+    #[test]
+    fn aborted_stream_names_the_failed_page() {
+        let failure = PageFailure {
+            page: 2,
+            stage: Stage::Ocr,
+            message: "engine\ncrashed".to_string(),
+        };
+        let markdown = stream_ending(StreamEnd::Aborted(&failure));
+        assert!(markdown.contains("<!-- incomplete: aborted at page 2 -->"));
+        assert!(markdown.contains("aborted at page 2, which failed during ocr: engine crashed."));
+        assert!(markdown.contains("Pages 2-4 were not converted."));
+        assert!(!markdown.contains("interrupted"));
+    }
+
+    #[test]
+    fn failed_stream_gives_the_error() {
+        let error = anyhow::anyhow!("disk full");
+        let markdown = stream_ending(StreamEnd::Failed(&error));
+        assert!(markdown.contains("<!-- incomplete: conversion failed -->"));
+        assert!(markdown.contains("failed before pages 2-4 were converted: disk full"));
+        assert!(!markdown.contains("--resume"));
+    }
+
+    #[test]
+    fn hyphen_breaks() {
+        assert!(is_hyphen_break("an exam-", "ple of"));
+        assert!(!is_hyphen_break("an exam-", "Ple"));
+        assert!(!is_hyphen_break("ranges 3-", "5"));
+        assert!(!is_hyphen_break("a dash -", "then"));
+        assert!(!is_hyphen_break("-", "word"));
+        assert!(!is_hyphen_break("co-", ""));
+    }
+
+    #[test]
+    fn sentence_boundaries() {
+        assert!(starts_mid_sentence("  and so on"));
+        assert!(starts_mid_sentence("中文继续"));
+        assert!(!starts_mid_sentence("The next"));
+        assert!(!starts_mid_sentence("1. Item"));
+        assert!(!starts_mid_sentence(""));
+
+        assert!(ends_sentence("Done."));
+        assert!(ends_sentence("完成。 "));
+        assert!(ends_sentence(""));
+        assert!(!ends_sentence("and then"));
+    }
+}
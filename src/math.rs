@@ -10,25 +10,24 @@ pub fn detect_formula_candidates(blocks: &[OcrBlock]) -> Vec<usize> {
     blocks
         .iter()
         .enumerate()
-        .filter_map(|(idx, block)| {
-            let text = block.text.trim();
-            if text.is_empty() {
-                return None;
-            }
-            let math_count = text.chars().filter(|c| MATH_SYMBOLS.contains(c)).count();
-            let len = text.chars().count().max(1);
-            let ratio = math_count as f32 / len as f32;
+        .filter_map(|(idx, block)| looks_like_formula(&block.text).then_some(idx))
+        .collect()
+}
 
-            let looks_like_inline_latex =
-                text.contains("\\frac") || text.contains("\\sum") || text.contains("\\int");
+/// Symbol-ratio and LaTeX keyword heuristic for a single line or block of text.
+pub fn looks_like_formula(text: &str) -> bool {
+    let text = text.trim();
+    if text.is_empty() {
+        return false;
+    }
+    let math_count = text.chars().filter(|c| MATH_SYMBOLS.contains(c)).count();
+    let len = text.chars().count().max(1);
+    let ratio = math_count as f32 / len as f32;
 
-            if ratio > 0.25 || looks_like_inline_latex {
-                Some(idx)
-            } else {
-                None
-            }
-        })
-        .collect()
+    let looks_like_inline_latex =
+        text.contains("\\frac") || text.contains("\\sum") || text.contains("\\int");
+
+    ratio > 0.25 || looks_like_inline_latex
 }
 
 pub fn wrap_formula(text: &str) -> String {
@@ -3,14 +3,27 @@ use image::DynamicImage;
 use paddle_ocr_rs::ocr_lite::OcrLite;
 use paddle_ocr_rs::ocr_result::TextBlock as PaddleTextBlock;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tesseract_rs::TesseractAPI;
 
 use crate::config::{Config, EngineKind};
 use crate::language::LanguageDetector;
 use crate::{layout, math};
 
+/// PaddleOCR detection, direction classification and recognition models, in that order.
+pub const PADDLE_MODEL_FILES: [&str; 3] = [
+    "ch_PP-OCRv4_det_infer.onnx",
+    "ch_ppocr_mobile_v2.0_cls_infer.onnx",
+    "ch_PP-OCRv4_rec_infer.onnx",
+];
+
+/// Languages always used for formula recognition and for language-detection samples.
+const FORMULA_LANGUAGES: &str = "equ+eng+chi_sim";
+const SAMPLE_LANGUAGES: &str = "eng+chi_sim";
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BoundingBox {
     pub x: u32,
@@ -24,6 +37,7 @@ pub enum BlockType {
     Text,
     Formula,
     Table,
+    Figure,
     Unknown,
 }
 
@@ -34,28 +48,42 @@ pub struct OcrBlock {
     pub bbox: Option<BoundingBox>,
     pub block_type: BlockType,
     pub language: Option<String>,
+    /// Asset file name for figure blocks
+    #[serde(default)]
+    pub image: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OcrPage {
     pub blocks: Vec<OcrBlock>,
     pub detected_language: Option<String>,
+    /// Time spent recognizing formula crops, included in the total recognize time
+    #[serde(skip)]
+    pub math_time: Duration,
+    /// Formula crops passed to `recognize_formula` and where they came from; only kept
+    /// with `debug_dir`
+    #[serde(skip)]
+    pub formula_crops: Vec<(BoundingBox, DynamicImage)>,
 }
 
+/// Cheap to clone: the Paddle models are shared.
+#[derive(Clone)]
 pub struct OcrEngine {
     primary: EngineKind,
-    languages: String,
     tessdata_dir: PathBuf,
     paddle: Option<Arc<Mutex<PaddleWrapper>>>,
     language_detector: LanguageDetector,
+    /// Set when a page was given up while this engine was recognizing it
+    abandoned: Arc<AtomicBool>,
 }
 
 impl OcrEngine {
     pub fn new(config: &Config) -> Result<Self> {
         let tessdata_dir = Self::get_tessdata_dir();
         // Verify Tesseract is present if needed for primary/math/lang detection
-        if matches!(config.engine, EngineKind::Tesseract) || config.math_ocr || config.detect_language {
-            Self::verify_tesseract(&tessdata_dir)?;
+        let languages = required_tesseract_languages(config);
+        if !languages.is_empty() {
+            Self::verify_tesseract(&tessdata_dir, &languages)?;
         }
 
         let paddle = if matches!(config.engine, EngineKind::Paddle) {
@@ -69,14 +97,29 @@ impl OcrEngine {
 
         Ok(Self {
             primary: config.engine,
-            languages: config.languages.clone(),
             tessdata_dir,
             paddle,
             language_detector: LanguageDetector::new(),
+            abandoned: Arc::default(),
         })
     }
 
-    fn get_tessdata_dir() -> PathBuf {
+    /// Record that a page was given up at its deadline while this engine was recognizing it.
+    pub(crate) fn abandon(&self) {
+        self.abandoned.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether a page given up at its deadline may still hold this engine, or a panic left
+    /// the Paddle models unusable; such an engine should be replaced.
+    pub fn is_stuck(&self) -> bool {
+        self.abandoned.load(Ordering::SeqCst)
+            || self
+                .paddle
+                .as_ref()
+                .is_some_and(|paddle| paddle.is_poisoned())
+    }
+
+    pub fn get_tessdata_dir() -> PathBuf {
         if let Ok(dir) = std::env::var("TESSDATA_PREFIX") {
             return PathBuf::from(dir);
         }
@@ -111,7 +154,8 @@ impl OcrEngine {
 
     pub fn recognize(&self, image: &DynamicImage, config: &Config) -> Result<OcrPage> {
         let mut detected_language = None;
-        let mut language_hint = self.languages.clone();
+        // Languages come from the per-call config so auto-config can tune them per document
+        let mut language_hint = config.languages.clone();
 
         if config.detect_language {
             if let Some(sample) = self.sample_language_text(image) {
@@ -132,6 +176,7 @@ impl OcrEngine {
                     bbox: None,
                     block_type: BlockType::Text,
                     language: detected_language.clone().or_else(|| Some(language_hint.clone())),
+                    image: None,
                 }]
             }
         };
@@ -140,6 +185,8 @@ impl OcrEngine {
             layout::sort_by_reading_order(&mut blocks);
         }
 
+        let math_start = Instant::now();
+        let mut formula_crops = Vec::new();
         if config.math_ocr {
             let candidates = math::detect_formula_candidates(&blocks);
             for idx in candidates {
@@ -148,6 +195,9 @@ impl OcrEngine {
                     let formula_text = self.recognize_formula(&crop)?;
                     blocks[idx].text = math::wrap_formula(&formula_text);
                     blocks[idx].block_type = BlockType::Formula;
+                    if config.debug_dir.is_some() {
+                        formula_crops.push((bbox, crop));
+                    }
                 } else {
                     blocks[idx].block_type = BlockType::Formula;
                 }
@@ -157,13 +207,14 @@ impl OcrEngine {
         Ok(OcrPage {
             blocks,
             detected_language,
+            math_time: math_start.elapsed(),
+            formula_crops,
         })
     }
 
     fn recognize_formula(&self, image: &DynamicImage) -> Result<String> {
         // Prefer math language pack if available
-        let formula_langs = "equ+eng+chi_sim";
-        self.recognize_with_tesseract(image, formula_langs)
+        self.recognize_with_tesseract(image, FORMULA_LANGUAGES)
     }
 
     fn recognize_with_tesseract(&self, image: &DynamicImage, languages: &str) -> Result<String> {
@@ -198,7 +249,7 @@ impl OcrEngine {
 
     fn sample_language_text(&self, image: &DynamicImage) -> Option<String> {
         let thumbnail = image.thumbnail(640, 640);
-        self.recognize_with_tesseract(&thumbnail, "eng+chi_sim")
+        self.recognize_with_tesseract(&thumbnail, SAMPLE_LANGUAGES)
             .ok()
             .map(|t| t.chars().take(400).collect())
     }
@@ -208,7 +259,9 @@ impl OcrEngine {
             .paddle
             .as_ref()
             .context("Paddle OCR not initialized. Set --engine paddle and ensure models exist.")?;
-        let mut guard = engine.lock().unwrap();
+        let mut guard = engine.lock().map_err(|_| {
+            anyhow::anyhow!("Paddle OCR engine unusable after a panic on an earlier page")
+        })?;
         let rgb = image.to_rgb8();
         let padding = guard.padding;
         let max_side_len = guard.max_side_len;
@@ -235,15 +288,29 @@ impl OcrEngine {
                 bbox,
                 block_type: BlockType::Text,
                 language: None,
+                image: None,
             });
         }
 
         Ok(blocks)
     }
 
-    fn verify_tesseract(tessdata_dir: &PathBuf) -> Result<()> {
+    fn verify_tesseract(tessdata_dir: &Path, languages: &[String]) -> Result<()> {
+        let missing: Vec<&str> = languages
+            .iter()
+            .filter(|lang| !tessdata_dir.join(format!("{}.traineddata", lang)).is_file())
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "Tesseract language data missing for: {} (looked in {})\nSet TESSDATA_PREFIX or run download_tessdata.ps1; `rust-ocr2md doctor` shows details",
+                missing.join(", "),
+                tessdata_dir.display()
+            );
+        }
+
         let api = TesseractAPI::new();
-        api.init(tessdata_dir.to_str().unwrap_or("."), "eng")
+        api.init(tessdata_dir.to_str().unwrap_or("."), &languages.join("+"))
             .map_err(|_| {
                 anyhow::anyhow!(
                     "Tesseract initialization failed. Ensure tessdata exists at: {}\nSet TESSDATA_PREFIX or run download_tessdata.ps1",
@@ -271,7 +338,43 @@ fn points_to_bbox(tb: &PaddleTextBlock) -> Option<BoundingBox> {
     })
 }
 
-fn crop_image(image: &DynamicImage, bbox: &BoundingBox) -> DynamicImage {
+/// Tesseract language packs a run with `config` will load, in first-use order.
+pub fn required_tesseract_languages(config: &Config) -> Vec<String> {
+    let mut groups = Vec::new();
+    if matches!(config.engine, EngineKind::Tesseract) {
+        groups.push(config.languages.as_str());
+    }
+    if config.detect_language {
+        groups.push(SAMPLE_LANGUAGES);
+    }
+    if config.math_ocr {
+        groups.push(FORMULA_LANGUAGES);
+    }
+
+    let mut languages: Vec<String> = Vec::new();
+    for lang in groups.iter().flat_map(|g| g.split('+')) {
+        let lang = lang.trim();
+        if !lang.is_empty() && !languages.iter().any(|l| l == lang) {
+            languages.push(lang.to_string());
+        }
+    }
+    languages
+}
+
+/// Model directory given on the command line, or `models/paddle` next to the executable.
+pub fn paddle_model_dir(model_dir: Option<PathBuf>) -> PathBuf {
+    model_dir.unwrap_or_else(|| {
+        // Prefer alongside executable to make drag-and-drop work
+        std::env::current_exe()
+            .ok()
+            .and_then(|p| p.parent().map(|d| d.to_path_buf()))
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("models")
+            .join("paddle")
+    })
+}
+
+pub(crate) fn crop_image(image: &DynamicImage, bbox: &BoundingBox) -> DynamicImage {
     let x = bbox.x.min(image.width().saturating_sub(1));
     let y = bbox.y.min(image.height().saturating_sub(1));
     let w = bbox.width.min(image.width().saturating_sub(x));
@@ -290,18 +393,8 @@ struct PaddleWrapper {
 
 impl PaddleWrapper {
     fn new(model_dir: Option<PathBuf>, threads: usize) -> Result<Self> {
-        let base_dir = model_dir.unwrap_or_else(|| {
-            // Prefer alongside executable to make drag-and-drop work
-            std::env::current_exe()
-                .ok()
-                .and_then(|p| p.parent().map(|d| d.to_path_buf()))
-                .unwrap_or_else(|| PathBuf::from("."))
-                .join("models")
-                .join("paddle")
-        });
-        let det = base_dir.join("ch_PP-OCRv4_det_infer.onnx");
-        let cls = base_dir.join("ch_ppocr_mobile_v2.0_cls_infer.onnx");
-        let rec = base_dir.join("ch_PP-OCRv4_rec_infer.onnx");
+        let base_dir = paddle_model_dir(model_dir);
+        let [det, cls, rec] = PADDLE_MODEL_FILES.map(|name| base_dir.join(name));
 
         if !det.exists() || !cls.exists() || !rec.exists() {
             anyhow::bail!(
@@ -0,0 +1,264 @@
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Guard against malformed outlines that loop back on themselves; counts every bookmark
+/// visited, including those without a title or destination.
+const MAX_OUTLINE_NODES: usize = 10_000;
+const MAX_OUTLINE_DEPTH: usize = 16;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutlineEntry {
+    pub title: String,
+    /// Nesting depth, 0 for top-level bookmarks
+    pub level: usize,
+    /// 1-based page number the bookmark points to
+    pub page_num: usize,
+    /// Vertical target as a fraction of page height from the top, if the destination has one
+    pub position: Option<f32>,
+}
+
+/// Read the document outline (bookmarks) in depth-first order.
+pub fn read_outline(document: &PdfDocument) -> Vec<OutlineEntry> {
+    let mut entries = Vec::new();
+    let mut visited = 0;
+    walk(
+        document.bookmarks().root(),
+        0,
+        &mut visited,
+        &mut |bookmark, level| {
+            if let Some(entry) = to_entry(document, bookmark, level) {
+                entries.push(entry);
+            }
+        },
+    );
+    entries
+}
+
+/// The links of a bookmark tree that the walk follows.
+trait OutlineNode: Sized {
+    fn first_child(&self) -> Option<Self>;
+    fn next_sibling(&self) -> Option<Self>;
+}
+
+impl OutlineNode for PdfBookmark<'_> {
+    fn first_child(&self) -> Option<Self> {
+        PdfBookmark::first_child(self)
+    }
+
+    fn next_sibling(&self) -> Option<Self> {
+        PdfBookmark::next_sibling(self)
+    }
+}
+
+fn walk<N: OutlineNode>(
+    first: Option<N>,
+    level: usize,
+    visited: &mut usize,
+    visit: &mut impl FnMut(&N, usize),
+) {
+    if level >= MAX_OUTLINE_DEPTH {
+        return;
+    }
+
+    let mut current = first;
+    while let Some(node) = current {
+        if *visited >= MAX_OUTLINE_NODES {
+            return;
+        }
+        *visited += 1;
+        visit(&node, level);
+        walk(node.first_child(), level + 1, visited, visit);
+        current = node.next_sibling();
+    }
+}
+
+fn to_entry(document: &PdfDocument, bookmark: &PdfBookmark, level: usize) -> Option<OutlineEntry> {
+    let title = bookmark.title()?.trim().to_string();
+    if title.is_empty() {
+        return None;
+    }
+
+    let (page_index, top) = match bookmark.destination() {
+        Some(destination) => destination_target(&destination)?,
+        None => {
+            let action = bookmark.action()?;
+            let destination = action.as_local_destination_action()?.destination().ok()?;
+            destination_target(&destination)?
+        }
+    };
+
+    let position = top.and_then(|top| {
+        let height = document.pages().page_size(page_index).ok()?.height().value;
+        (height > 0.0).then(|| ((height - top.value) / height).clamp(0.0, 1.0))
+    });
+
+    Some(OutlineEntry {
+        title,
+        level,
+        page_num: page_index as usize + 1,
+        position,
+    })
+}
+
+fn destination_target(destination: &PdfDestination) -> Option<(PdfPageIndex, Option<PdfPoints>)> {
+    let page_index = destination.page_index().ok()?;
+    let top = match destination.view_settings() {
+        Ok(PdfDestinationViewSettings::SpecificCoordinatesAndZoom(_, top, _))
+        | Ok(PdfDestinationViewSettings::FitPageHorizontallyToWindow(top))
+        | Ok(PdfDestinationViewSettings::FitBoundsHorizontallyToWindow(top)) => top,
+        _ => None,
+    };
+    Some((page_index, top))
+}
+
+/// GitHub-style heading anchors, de-duplicated with numeric suffixes.
+pub struct AnchorGenerator {
+    seen: HashMap<String, usize>,
+}
+
+impl AnchorGenerator {
+    pub fn new() -> Self {
+        Self {
+            seen: HashMap::new(),
+        }
+    }
+
+    pub fn anchor(&mut self, title: &str) -> String {
+        let slug: String = title
+            .trim()
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                '-' | '_' => Some(c),
+                c if c.is_alphanumeric() => Some(c),
+                _ => None,
+            })
+            .collect();
+
+        let count = self.seen.entry(slug.clone()).or_insert(0);
+        let anchor = if *count == 0 {
+            slug
+        } else {
+            format!("{}-{}", slug, count)
+        };
+        *count += 1;
+        anchor
+    }
+}
+
+impl Default for AnchorGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A bookmark tree `depth` levels deep with `siblings` bookmarks per level; `None` for
+    /// either makes it endless, like an outline whose links loop back.
+    #[derive(Clone, Copy)]
+    struct Node {
+        level: usize,
+        index: usize,
+        depth: Option<usize>,
+        siblings: Option<usize>,
+    }
+
+    impl Node {
+        fn root(depth: Option<usize>, siblings: Option<usize>) -> Option<Self> {
+            Some(Self {
+                level: 0,
+                index: 0,
+                depth,
+                siblings,
+            })
+        }
+    }
+
+    impl OutlineNode for Node {
+        fn first_child(&self) -> Option<Self> {
+            self.depth
+                .is_none_or(|d| self.level + 1 < d)
+                .then_some(Self {
+                    level: self.level + 1,
+                    index: 0,
+                    ..*self
+                })
+        }
+
+        fn next_sibling(&self) -> Option<Self> {
+            self.siblings
+                .is_none_or(|s| self.index + 1 < s)
+                .then_some(Self {
+                    index: self.index + 1,
+                    ..*self
+                })
+        }
+    }
+
+    /// Levels of the visited nodes in visiting order.
+    fn visit(root: Option<Node>) -> Vec<usize> {
+        let mut levels = Vec::new();
+        let mut visited = 0;
+        walk(root, 0, &mut visited, &mut |node: &Node, level| {
+            assert_eq!(node.level, level);
+            levels.push(level);
+        });
+        levels
+    }
+
+    #[test]
+    fn walks_depth_first() {
+        assert_eq!(
+            visit(Node::root(Some(3), Some(2))),
+            vec![0, 1, 2, 2, 1, 2, 2, 0, 1, 2, 2, 1, 2, 2]
+        );
+        assert!(visit(None).is_empty());
+    }
+
+    #[test]
+    fn stops_at_the_depth_limit() {
+        let levels = visit(Node::root(Some(100), Some(1)));
+        assert_eq!(levels, (0..MAX_OUTLINE_DEPTH).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stops_at_the_node_limit() {
+        assert_eq!(visit(Node::root(Some(1), None)).len(), MAX_OUTLINE_NODES);
+
+        let levels = visit(Node::root(None, None));
+        assert_eq!(levels.len(), MAX_OUTLINE_NODES);
+        assert_eq!(levels.iter().max(), Some(&(MAX_OUTLINE_DEPTH - 1)));
+    }
+
+    #[test]
+    fn slugs_titles_like_github() {
+        let mut anchors = AnchorGenerator::new();
+        assert_eq!(anchors.anchor("  1.2 Related Work  "), "12-related-work");
+        assert_eq!(
+            anchors.anchor("snake_case & kebab-case"),
+            "snake_case--kebab-case"
+        );
+        assert_eq!(anchors.anchor("What's new?"), "whats-new");
+    }
+
+    #[test]
+    fn keeps_unicode_letters() {
+        let mut anchors = AnchorGenerator::new();
+        assert_eq!(anchors.anchor("第一章 引言"), "第一章-引言");
+        assert_eq!(anchors.anchor("Über Ärger"), "über-ärger");
+    }
+
+    #[test]
+    fn numbers_duplicate_anchors() {
+        let mut anchors = AnchorGenerator::new();
+        assert_eq!(anchors.anchor("Results"), "results");
+        assert_eq!(anchors.anchor("results"), "results-1");
+        assert_eq!(anchors.anchor("Results!"), "results-2");
+        assert_eq!(anchors.anchor("Methods"), "methods");
+    }
+}
@@ -0,0 +1,234 @@
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+/// Parse a page selection into sorted, de-duplicated 1-based page numbers.
+///
+/// Comma-separated tokens, each one of:
+/// - `all`, `odd`, `even`
+/// - a page: `7`, a page label such as `iv`, `last`, or `last-2` (counting back from the end)
+/// - a range of those: `3-9`, `10-` (to the end), `-5` (from the start), `iii-x`, `last-4-last`
+/// - any of the above prefixed with `!` to exclude it, e.g. `1-100,!50-60`
+///
+/// A selection made only of exclusions starts from all pages.
+pub fn parse_page_range(
+    range_str: &str,
+    total_pages: usize,
+    page_labels: &[Option<String>],
+) -> Result<Vec<usize>> {
+    let parser = Parser {
+        total_pages,
+        page_labels,
+    };
+
+    let mut included = BTreeSet::new();
+    let mut excluded = BTreeSet::new();
+    let mut has_inclusions = false;
+
+    for raw in range_str.split(',') {
+        let token = raw.trim();
+        let (negated, body) = match token.strip_prefix('!') {
+            Some(rest) => (true, rest.trim()),
+            None => (false, token),
+        };
+
+        let pages = parser.parse_token(body).map_err(|reason| {
+            anyhow::anyhow!(
+                "Invalid page selection \"{}\": bad token \"{}\" ({})",
+                range_str,
+                token,
+                reason
+            )
+        })?;
+
+        if negated {
+            excluded.extend(pages);
+        } else {
+            has_inclusions = true;
+            included.extend(pages);
+        }
+    }
+
+    if !has_inclusions {
+        included.extend(1..=total_pages);
+    }
+
+    let pages: Vec<usize> = included.difference(&excluded).copied().collect();
+    if pages.is_empty() {
+        anyhow::bail!("Page selection \"{}\" selects no pages", range_str);
+    }
+    Ok(pages)
+}
+
+struct Parser<'a> {
+    total_pages: usize,
+    page_labels: &'a [Option<String>],
+}
+
+impl Parser<'_> {
+    fn parse_token(&self, token: &str) -> std::result::Result<Vec<usize>, String> {
+        match token.to_ascii_lowercase().as_str() {
+            "" => return Err("empty token".to_string()),
+            "all" => return Ok((1..=self.total_pages).collect()),
+            "odd" => return Ok((1..=self.total_pages).step_by(2).collect()),
+            "even" => return Ok((2..=self.total_pages).step_by(2).collect()),
+            _ => {}
+        }
+
+        // A single page first: labels and `last-N` may themselves contain '-'
+        if let Some(page) = self.parse_endpoint(token)? {
+            return Ok(vec![page]);
+        }
+
+        // Otherwise try every '-' as the range separator until both sides make sense
+        for (idx, _) in token.match_indices('-') {
+            let (start, end) = (&token[..idx], &token[idx + 1..]);
+            let start = if start.trim().is_empty() {
+                Some(1)
+            } else {
+                self.parse_endpoint(start)?
+            };
+            let end = if end.trim().is_empty() {
+                Some(self.total_pages)
+            } else {
+                self.parse_endpoint(end)?
+            };
+
+            if let (Some(start), Some(end)) = (start, end) {
+                if start > end {
+                    return Err(format!("range start {} is after end {}", start, end));
+                }
+                return Ok((start..=end).collect());
+            }
+        }
+
+        Err("expected a page number, page label, range, all, odd, even or last".to_string())
+    }
+
+    /// A single page position. `Ok(None)` means the text is not a page at all.
+    ///
+    /// Physical page numbers take precedence; anything else is looked up as a page label.
+    fn parse_endpoint(&self, text: &str) -> std::result::Result<Option<usize>, String> {
+        let text = text.trim();
+
+        if let Ok(page) = text.parse::<usize>() {
+            return self.check(page).map(Some);
+        }
+
+        let lower = text.to_ascii_lowercase();
+        if lower == "last" {
+            return self.check(self.total_pages).map(Some);
+        }
+        if let Some(offset) = lower.strip_prefix("last-") {
+            if let Ok(offset) = offset.trim().parse::<usize>() {
+                return match self.total_pages.checked_sub(offset) {
+                    Some(page) if page >= 1 => Ok(Some(page)),
+                    _ => Err(format!(
+                        "{} is before the first page ({} pages)",
+                        text, self.total_pages
+                    )),
+                };
+            }
+        }
+
+        Ok(find_page_label(text, self.page_labels))
+    }
+
+    fn check(&self, page: usize) -> std::result::Result<usize, String> {
+        if page < 1 || page > self.total_pages {
+            return Err(format!(
+                "page {} out of range (1-{})",
+                page, self.total_pages
+            ));
+        }
+        Ok(page)
+    }
+}
+
+fn find_page_label(label: &str, page_labels: &[Option<String>]) -> Option<usize> {
+    page_labels
+        .iter()
+        .position(|l| {
+            l.as_deref()
+                .is_some_and(|l| l.eq_ignore_ascii_case(label.trim()))
+        })
+        .map(|idx| idx + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages(selection: &str, total: usize) -> Vec<usize> {
+        parse_page_range(selection, total, &[]).unwrap()
+    }
+
+    fn error(selection: &str, total: usize) -> String {
+        parse_page_range(selection, total, &[])
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn lists_and_ranges() {
+        assert_eq!(pages("1,3,5", 10), [1, 3, 5]);
+        assert_eq!(pages("3-5", 10), [3, 4, 5]);
+        assert_eq!(pages(" 2 - 3 , 7 ", 10), [2, 3, 7]);
+        assert_eq!(pages("5,1,3-4,4", 10), [1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(pages("all", 3), [1, 2, 3]);
+        assert_eq!(pages("ALL", 3), [1, 2, 3]);
+        assert_eq!(pages("odd", 5), [1, 3, 5]);
+        assert_eq!(pages("even", 5), [2, 4]);
+    }
+
+    #[test]
+    fn open_ranges() {
+        assert_eq!(pages("8-", 10), [8, 9, 10]);
+        assert_eq!(pages("-3", 10), [1, 2, 3]);
+    }
+
+    #[test]
+    fn counting_from_the_end() {
+        assert_eq!(pages("last", 10), [10]);
+        assert_eq!(pages("last-2", 10), [8]);
+        assert_eq!(pages("last-2-last", 10), [8, 9, 10]);
+        assert_eq!(pages("7-last", 10), [7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn exclusions() {
+        assert_eq!(pages("1-10,!3-5", 10), [1, 2, 6, 7, 8, 9, 10]);
+        assert_eq!(pages("!1", 3), [2, 3]);
+        assert_eq!(pages("!odd", 5), [2, 4]);
+    }
+
+    #[test]
+    fn page_labels() {
+        let labels: Vec<Option<String>> = ["i", "ii", "iii", "1", "2"]
+            .iter()
+            .map(|l| Some(l.to_string()))
+            .collect();
+        let pages = |selection: &str| parse_page_range(selection, 5, &labels).unwrap();
+        assert_eq!(pages("ii"), [2]);
+        assert_eq!(pages("II"), [2]);
+        assert_eq!(pages("i-iii"), [1, 2, 3]);
+        // Physical page numbers win over labels
+        assert_eq!(pages("1"), [1]);
+        assert_eq!(pages("iii-last"), [3, 4, 5]);
+    }
+
+    #[test]
+    fn invalid_selections() {
+        assert!(error("0", 10).contains("out of range"));
+        assert!(error("11", 10).contains("out of range"));
+        assert!(error("5-3", 10).contains("range start 5 is after end 3"));
+        assert!(error("last-10", 10).contains("before the first page"));
+        assert!(error("abc", 10).contains("bad token \"abc\""));
+        assert!(error("", 10).contains("empty token"));
+        assert!(error("1,,2", 10).contains("empty token"));
+        assert!(error("!1-3", 3).contains("selects no pages"));
+    }
+}
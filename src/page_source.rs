@@ -0,0 +1,975 @@
+use crate::annotations::{PageAnnotation, PageLink};
+use crate::cache::CacheManager;
+use crate::config::{Config, ErrorPolicy};
+use crate::debug;
+use crate::error::{self, OcrError, PageFailure, Stage, StageContext};
+use crate::figures;
+use crate::image_processor;
+use crate::ocr_engine::{self, BlockType, BoundingBox, OcrBlock, OcrEngine};
+use crate::outline::OutlineEntry;
+use crate::pdf_processor::{DocumentInfo, PageResult, PdfProcessor};
+use crate::progress::{self, Event, ProgressHandler};
+use crate::report::{PageReport, PageTimings};
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma, LumaA, Rgb, Rgba};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::BufReader;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tiff::decoder::{Decoder as TiffDecoder, DecodingResult};
+use tiff::ColorType as TiffColorType;
+
+/// Raster image extensions accepted as input, besides PDF.
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "tif", "tiff", "bmp", "webp"];
+
+/// A rendered page plus anything the source knows about it beyond the pixels.
+pub struct RenderedPage {
+    pub image: DynamicImage,
+    /// Regions known to hold images (e.g. PDF image objects), in pixels
+    pub image_regions: Vec<BoundingBox>,
+    pub links: Vec<PageLink>,
+    pub annotations: Vec<PageAnnotation>,
+    /// Problems that did not stop the page, e.g. a lowered DPI
+    pub warnings: Vec<String>,
+}
+
+impl RenderedPage {
+    pub fn from_image(image: DynamicImage) -> Self {
+        Self {
+            image,
+            image_regions: Vec::new(),
+            links: Vec::new(),
+            annotations: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// Highest DPI, up to `dpi`, at which a page of the given size in points stays within
+/// `max_pixels` (0 = no limit).
+pub fn capped_dpi(width_pt: f32, height_pt: f32, dpi: u32, max_pixels: u64) -> u32 {
+    let pixels = (width_pt as f64 * dpi as f64 / 72.0) * (height_pt as f64 * dpi as f64 / 72.0);
+    if max_pixels == 0 || pixels <= max_pixels as f64 {
+        return dpi;
+    }
+    let scale = (max_pixels as f64 / pixels).sqrt();
+    ((dpi as f64 * scale).floor() as u32).max(1)
+}
+
+/// Anything that can produce page images for the preprocess/OCR/Markdown pipeline.
+///
+/// Pages are rendered on a worker thread when `page_timeout` is set.
+pub trait PageSource: Send + Sync {
+    /// Input path, used for display and cache keys.
+    fn path(&self) -> &Path;
+
+    fn page_count(&self) -> usize;
+
+    /// Render a 1-based page.
+    fn render_page(&self, page_num: usize, config: &Config) -> Result<RenderedPage>;
+
+    /// Document bookmarks, in depth-first order.
+    fn outline(&self) -> &[OutlineEntry] {
+        &[]
+    }
+
+    fn info(&self) -> DocumentInfo {
+        DocumentInfo::default()
+    }
+
+    /// Page labels indexed by page number - 1.
+    fn page_labels(&self) -> &[Option<String>] {
+        &[]
+    }
+
+    /// Embedded text of a 1-based page, if the source has a text layer.
+    fn text_layer(&self, _page_num: usize) -> Option<String> {
+        None
+    }
+}
+
+/// Open a PDF, an image file, a multi-page TIFF or a directory of images.
+pub fn open(path: &Path, password: Option<String>) -> Result<Box<dyn PageSource>> {
+    if path.is_dir() {
+        return Ok(Box::new(ImageSource::from_dir(path)?));
+    }
+
+    match extension(path).as_deref() {
+        Some("pdf") => Ok(Box::new(PdfProcessor::new(path, password)?)),
+        Some(ext) if IMAGE_EXTENSIONS.contains(&ext) => Ok(Box::new(ImageSource::from_file(path)?)),
+        _ => anyhow::bail!(
+            "Unsupported input (expected a PDF, an image [{}] or a directory of images): {}",
+            IMAGE_EXTENSIONS.join(", "),
+            path.display()
+        ),
+    }
+}
+
+/// Open a PDF held in memory; `name` is used for display and cache keys.
+pub fn from_bytes(
+    bytes: Vec<u8>,
+    name: &Path,
+    password: Option<String>,
+) -> Result<Box<dyn PageSource>> {
+    // The header may follow up to 1 KB of leading garbage
+    if !bytes[..bytes.len().min(1024)]
+        .windows(4)
+        .any(|w| w == b"%PDF")
+    {
+        anyhow::bail!(
+            "{} does not contain a PDF (only PDFs can be read from stdin or memory)",
+            name.display()
+        );
+    }
+    Ok(Box::new(PdfProcessor::from_bytes(bytes, name, password)?))
+}
+
+/// Pages run through the pipeline.
+pub struct ProcessedPages {
+    /// Pages that converted, in page order
+    pub results: Vec<PageResult>,
+    /// Pages that failed, in page order
+    pub failures: Vec<PageFailure>,
+    /// A diagnostic report for every page
+    pub reports: Vec<PageReport>,
+    /// Pages never started because the run was cancelled
+    pub interrupted: Vec<usize>,
+}
+
+/// A page leaving the pipeline, as passed to the `process_pages` callback.
+pub enum PageOutcome<'a> {
+    Done(&'a PageResult),
+    Failed(&'a PageFailure),
+}
+
+/// Settings that change what OCR produces for a page, used in cache keys and manifests.
+pub fn config_fingerprint(config: &Config) -> String {
+    format!(
+        "engine:{:?}|langs:{}|preprocess:{}|math:{}|layout:{}|gpu:{}|figures:{}",
+        config.engine,
+        config.languages,
+        config.preprocess,
+        config.math_ocr,
+        config.layout,
+        config.use_gpu,
+        config.figures
+    )
+}
+
+/// Run every page through the pipeline, handing each page to `on_page` as it finishes.
+///
+/// Failed pages are collected unless `config.on_error` is `abort`, in which case the
+/// first failure is returned as the error. Once `cancel` is set the current page is
+/// finished and the rest are returned as `interrupted`. After a page times out while
+/// rendering or in OCR, the remaining pages fail without being started.
+pub fn process_pages(
+    source: &Arc<dyn PageSource>,
+    page_range: &[usize],
+    ocr_engine: &OcrEngine,
+    config: &Config,
+    progress: &dyn ProgressHandler,
+    cancel: &AtomicBool,
+    on_page: &mut dyn FnMut(PageOutcome) -> Result<()>,
+) -> Result<ProcessedPages> {
+    let mut results = Vec::new();
+    let mut failures = Vec::new();
+    let mut reports = Vec::with_capacity(page_range.len());
+    let cache = CacheManager::new(&config.cache)?.with_document(source.path());
+
+    // Pages beyond the page limit fail up front without being rendered
+    let (page_range, over_limit) = if config.max_pages > 0 && page_range.len() > config.max_pages {
+        if config.on_error == ErrorPolicy::Abort {
+            return Err(OcrError::LimitExceeded(format!(
+                "{} pages selected, --max-pages is {}",
+                page_range.len(),
+                config.max_pages
+            ))
+            .into());
+        }
+        page_range.split_at(config.max_pages)
+    } else {
+        (page_range, &[][..])
+    };
+
+    let mut pipeline = Pipeline {
+        source,
+        ocr_engine,
+        // Shared with the page worker thread instead of copied for every page
+        config: Arc::new(config.clone()),
+        cache: &cache,
+        worker: StageWorker::new(),
+    };
+    let mut interrupted = Vec::new();
+    for (idx, &page_num) in page_range.iter().enumerate() {
+        if cancel.load(AtomicOrdering::SeqCst) {
+            interrupted = page_range[idx..].to_vec();
+            interrupted.extend(over_limit);
+            progress.on_event(&Event::Interrupted {
+                remaining: interrupted.len(),
+            });
+            break;
+        }
+
+        let page_start = Instant::now();
+        let mut timings = PageTimings::default();
+        let mut warnings = Vec::new();
+        progress.on_event(&Event::PageStarted { page: page_num });
+        let outcome = pipeline.process_page(page_num, &mut timings, &mut warnings);
+        timings.total_ms = progress::millis(page_start.elapsed());
+
+        match outcome {
+            Ok((result, cache_hit)) => {
+                if cache_hit {
+                    progress.on_event(&Event::CacheHit { page: page_num });
+                }
+                progress.on_event(&Event::PageFinished {
+                    page: page_num,
+                    blocks: result.blocks.len(),
+                    cache_hit,
+                    elapsed_ms: timings.total_ms,
+                });
+                reports.push(PageReport::success(&result, cache_hit, timings, warnings));
+                on_page(PageOutcome::Done(&result))?;
+                results.push(result);
+            }
+            Err(e) => {
+                let failure = match e {
+                    OcrError::PageFailed(failure) => failure,
+                    other => PageFailure {
+                        page: page_num,
+                        stage: Stage::Ocr,
+                        message: other.to_string(),
+                    },
+                };
+                progress.on_event(&Event::PageError {
+                    page: page_num,
+                    error: failure.to_string(),
+                    elapsed_ms: timings.total_ms,
+                });
+                reports.push(PageReport::failure(&failure, timings));
+                if config.on_error == ErrorPolicy::Abort {
+                    return Err(OcrError::PageFailed(failure).into());
+                }
+                on_page(PageOutcome::Failed(&failure))?;
+                failures.push(failure);
+            }
+        }
+    }
+
+    for &page_num in over_limit.iter().filter(|_| interrupted.is_empty()) {
+        let failure = PageFailure {
+            page: page_num,
+            stage: Stage::Limits,
+            message: format!("beyond --max-pages {}", config.max_pages),
+        };
+        progress.on_event(&Event::PageError {
+            page: page_num,
+            error: failure.to_string(),
+            elapsed_ms: 0,
+        });
+        reports.push(PageReport::failure(&failure, PageTimings::default()));
+        on_page(PageOutcome::Failed(&failure))?;
+        failures.push(failure);
+    }
+    results.sort_by_key(|r| r.page_num);
+
+    match cache.enforce_limit() {
+        Ok(removed) if removed.entries > 0 => progress.on_event(&Event::Info {
+            message: format!(
+                "🧹 Cache over {} MB: removed {} least recently used pages",
+                config.cache.max_size_mb, removed.entries
+            ),
+        }),
+        Ok(_) => {}
+        Err(e) => progress.on_event(&Event::Info {
+            message: format!("⚠️  Cache size limit not applied: {:#}", e),
+        }),
+    }
+
+    Ok(ProcessedPages {
+        results,
+        failures,
+        reports,
+        interrupted,
+    })
+}
+
+/// What the pages of one document share on their way through the pipeline.
+struct Pipeline<'a> {
+    source: &'a Arc<dyn PageSource>,
+    ocr_engine: &'a OcrEngine,
+    config: Arc<Config>,
+    cache: &'a CacheManager,
+    worker: StageWorker,
+}
+
+impl Pipeline<'_> {
+    /// Convert one page; the flag is set when its OCR result came from the cache.
+    fn process_page(
+        &mut self,
+        page_num: usize,
+        timings: &mut PageTimings,
+        warnings: &mut Vec<String>,
+    ) -> error::Result<(PageResult, bool)> {
+        // Held apart from `self`, which the stage worker borrows mutably
+        let shared_config = Arc::clone(&self.config);
+        let config: &Config = &shared_config;
+        let (source, cache) = (self.source, self.cache);
+
+        let deadline = (config.page_timeout > 0)
+            .then(|| Instant::now() + Duration::from_secs(config.page_timeout));
+
+        let stage = Instant::now();
+        let (render_source, render_config) = (Arc::clone(self.source), Arc::clone(&shared_config));
+        let RenderedPage {
+            image,
+            image_regions,
+            links,
+            annotations: page_annotations,
+            warnings: render_warnings,
+        } = self
+            .worker
+            .run(page_num, Stage::Render, deadline, config, move || {
+                render_source.render_page(page_num, &render_config)
+            })?;
+        timings.render_ms = progress::millis(stage.elapsed());
+        warnings.extend(render_warnings);
+        check_deadline(deadline, page_num, Stage::Render, config)?;
+
+        let raw_bytes = image.to_rgba8().into_raw();
+        let config_fingerprint = config_fingerprint(config);
+        let page_hash = if cache.enabled() {
+            Some(cache.make_hash(
+                source.path(),
+                page_num,
+                config.dpi,
+                &config_fingerprint,
+                &raw_bytes,
+            ))
+        } else {
+            None
+        };
+
+        // Debug output needs a fresh OCR run; the result is still cached for later runs
+        if let Some(hash) = page_hash.as_ref().filter(|_| config.debug_dir.is_none()) {
+            if let Some(mut cached) =
+                cache_warning(cache.load_page(hash), "read", warnings).flatten()
+            {
+                // Assets may have been removed or written elsewhere since the page was cached
+                save_figure_assets(&image, &cached.blocks, &config.assets_dir)
+                    .stage(page_num, Stage::Figures)?;
+                // Annotations are cheap to read, so they are never taken from the cache
+                cached.links = links;
+                cached.annotations = page_annotations;
+                return Ok((cached, true));
+            }
+        }
+
+        let stage = Instant::now();
+        let processed_image = if config.preprocess {
+            let cached = match (page_hash.as_ref(), cache.enabled()) {
+                (Some(hash), true) => {
+                    cache_warning(cache.load_preprocessed(hash), "read", warnings).flatten()
+                }
+                _ => None,
+            };
+            match cached {
+                Some(img) => img,
+                None => {
+                    let img = image_processor::preprocess_image(image.clone(), config.use_gpu)
+                        .stage(page_num, Stage::Preprocess)?;
+                    if let (Some(hash), true) = (page_hash.as_ref(), cache.enabled()) {
+                        cache_warning(
+                            cache.store_preprocessed(hash, page_num, &img),
+                            "write",
+                            warnings,
+                        );
+                    }
+                    img
+                }
+            }
+        } else {
+            image.clone()
+        };
+
+        timings.preprocess_ms = progress::millis(stage.elapsed());
+        check_deadline(deadline, page_num, Stage::Preprocess, config)?;
+
+        let stage = Instant::now();
+        let (engine, ocr_config) = (self.ocr_engine.clone(), Arc::clone(&shared_config));
+        let recognized = self
+            .worker
+            .run(page_num, Stage::Ocr, deadline, config, move || {
+                let page = engine.recognize(&processed_image, &ocr_config)?;
+                Ok((processed_image, page))
+            });
+        if self.worker.gave_up(Stage::Ocr) {
+            // The abandoned call keeps the models busy; the next document loads new ones
+            self.ocr_engine.abandon();
+        }
+        let (processed_image, mut ocr_page) = recognized?;
+        let ocr_time = stage.elapsed();
+        timings.math_ms = progress::millis(ocr_page.math_time);
+        timings.ocr_ms = progress::millis(ocr_time.saturating_sub(ocr_page.math_time));
+
+        let stage = Instant::now();
+        if config.figures {
+            let regions = figures::detect_regions(&image, &ocr_page.blocks, &image_regions);
+            figures::attach_figures(&mut ocr_page.blocks, &regions, image.height(), |idx| {
+                format!("page-{}-fig-{}.png", page_num, idx)
+            });
+            save_figure_assets(&image, &ocr_page.blocks, &config.assets_dir)
+                .stage(page_num, Stage::Figures)?;
+        }
+        timings.figures_ms = progress::millis(stage.elapsed());
+        check_deadline(deadline, page_num, Stage::Figures, config)?;
+
+        if let Some(dir) = &config.debug_dir {
+            if let Err(e) = debug::write_page(
+                dir,
+                page_num,
+                &processed_image,
+                &ocr_page.blocks,
+                &ocr_page.formula_crops,
+            ) {
+                warnings.push(format!("Debug output not written: {:#}", e));
+            }
+        }
+
+        let result = PageResult {
+            page_num,
+            blocks: ocr_page.blocks,
+            detected_language: ocr_page.detected_language,
+            image_width: processed_image.width(),
+            image_height: processed_image.height(),
+            links,
+            annotations: page_annotations,
+        };
+
+        if let Some(hash) = page_hash {
+            cache_warning(cache.store_page(&hash, &result), "write", warnings);
+        }
+
+        Ok((result, false))
+    }
+}
+
+/// A cache that cannot be read or written never fails a page: a read is treated as a miss
+/// and a write is skipped, with a page warning either way.
+fn cache_warning<T>(result: Result<T>, action: &str, warnings: &mut Vec<String>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warnings.push(format!("Cache {} failed: {:#}", action, e));
+            None
+        }
+    }
+}
+
+/// Fail the page if it has run past its deadline; checked between stages.
+fn check_deadline(
+    deadline: Option<Instant>,
+    page_num: usize,
+    stage: Stage,
+    config: &Config,
+) -> error::Result<()> {
+    match deadline {
+        Some(deadline) if Instant::now() > deadline => Err(timed_out(page_num, stage, config)),
+        _ => Ok(()),
+    }
+}
+
+fn timed_out(page_num: usize, stage: Stage, config: &Config) -> OcrError {
+    page_failed(
+        page_num,
+        stage,
+        format!("timed out after {}s (--page-timeout)", config.page_timeout),
+    )
+}
+
+fn page_failed(page: usize, stage: Stage, message: String) -> OcrError {
+    OcrError::PageFailed(PageFailure {
+        page,
+        stage,
+        message,
+    })
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs the stages that can hang in native code, rendering and OCR, on a worker thread so
+/// a page can be given up at its deadline.
+///
+/// A stage that was given up cannot be interrupted and keeps PDFium or the OCR engine busy,
+/// so later pages fail at once instead of queueing behind it. The worker thread ends when
+/// that stage returns.
+struct StageWorker {
+    jobs: Option<mpsc::Sender<Job>>,
+    /// Page and stage that were given up
+    stuck: Option<(usize, Stage)>,
+}
+
+impl StageWorker {
+    fn new() -> Self {
+        Self {
+            jobs: None,
+            stuck: None,
+        }
+    }
+
+    /// Run `job` for a stage of `page_num`; inline when there is no deadline.
+    fn run<T: Send + 'static>(
+        &mut self,
+        page_num: usize,
+        stage: Stage,
+        deadline: Option<Instant>,
+        config: &Config,
+        job: impl FnOnce() -> Result<T> + Send + 'static,
+    ) -> error::Result<T> {
+        if let Some((stuck_page, stuck_stage)) = self.stuck {
+            return Err(page_failed(
+                page_num,
+                stage,
+                format!(
+                    "not started: page {} timed out during {} and is still running (--page-timeout)",
+                    stuck_page, stuck_stage
+                ),
+            ));
+        }
+        let Some(deadline) = deadline else {
+            return job().stage(page_num, stage);
+        };
+
+        let (tx, rx) = mpsc::channel();
+        self.sender()
+            .and_then(|jobs| {
+                jobs.send(Box::new(move || {
+                    let _ = tx.send(job());
+                }))
+                .map_err(|_| anyhow::anyhow!("Page worker thread stopped"))
+            })
+            .stage(page_num, stage)?;
+
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(result) => result.stage(page_num, stage),
+            Err(RecvTimeoutError::Timeout) => {
+                self.stuck = Some((page_num, stage));
+                // The thread exits once the abandoned stage returns
+                self.jobs = None;
+                Err(timed_out(page_num, stage, config))
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                // The panic ended the thread; the next page starts a new one
+                self.jobs = None;
+                Err(page_failed(
+                    page_num,
+                    stage,
+                    "page worker thread panicked".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Whether a page was given up during `stage`.
+    fn gave_up(&self, stage: Stage) -> bool {
+        self.stuck
+            .is_some_and(|(_, stuck_stage)| stuck_stage == stage)
+    }
+
+    fn sender(&mut self) -> Result<mpsc::Sender<Job>> {
+        if let Some(jobs) = &self.jobs {
+            return Ok(jobs.clone());
+        }
+        let (tx, rx) = mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("page-worker".to_string())
+            .spawn(move || {
+                for job in rx {
+                    job();
+                }
+            })
+            .context("Failed to start page worker thread")?;
+        self.jobs = Some(tx.clone());
+        Ok(tx)
+    }
+}
+
+/// Crop figure blocks out of the rendered page and write them as assets.
+///
+/// Existing files are overwritten: names only carry page and figure numbers, so a file
+/// left from another document or an earlier version of this one must not be linked.
+fn save_figure_assets(image: &DynamicImage, blocks: &[OcrBlock], assets_dir: &Path) -> Result<()> {
+    for block in blocks.iter().filter(|b| b.block_type == BlockType::Figure) {
+        let (Some(name), Some(bbox)) = (&block.image, &block.bbox) else {
+            continue;
+        };
+        let path = assets_dir.join(name);
+        std::fs::create_dir_all(assets_dir).with_context(|| {
+            format!("Failed to create assets directory {}", assets_dir.display())
+        })?;
+        ocr_engine::crop_image(image, bbox)
+            .save(&path)
+            .with_context(|| format!("Failed to write figure {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// One page of an image input: a file, or one frame of a multi-page TIFF.
+struct ImagePage {
+    file: PathBuf,
+    frame: Option<usize>,
+}
+
+/// Scanned pages delivered as image files instead of a PDF.
+pub struct ImageSource {
+    path: PathBuf,
+    pages: Vec<ImagePage>,
+    /// Decoder of the TIFF read last, kept open for the next frame
+    tiff: Mutex<Option<OpenTiff>>,
+}
+
+/// A multi-page TIFF being read: pages arrive in order, so each frame is reached by
+/// stepping from the previous one instead of from the start of the file.
+struct OpenTiff {
+    file: PathBuf,
+    /// Frame the decoder is at
+    frame: usize,
+    decoder: TiffDecoder<BufReader<File>>,
+}
+
+impl ImageSource {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            pages: image_pages(path)?,
+            tiff: Mutex::new(None),
+        })
+    }
+
+    /// All supported images in a directory, in natural filename order (page2 before page10).
+    pub fn from_dir(dir: &Path) -> Result<Self> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read directory {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.is_file()
+                    && extension(p).is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+            })
+            .collect();
+        files.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+
+        if files.is_empty() {
+            anyhow::bail!("No image files found in {}", dir.display());
+        }
+
+        let mut pages = Vec::new();
+        for file in &files {
+            pages.extend(image_pages(file)?);
+        }
+
+        Ok(Self {
+            path: dir.to_path_buf(),
+            pages,
+            tiff: Mutex::new(None),
+        })
+    }
+
+    fn read_tiff_frame(&self, file: &Path, frame: usize) -> Result<DynamicImage> {
+        let mut open = self.tiff.lock().unwrap_or_else(PoisonError::into_inner);
+        // Taken out while in use, so a failed read leaves no half-read decoder behind; an
+        // earlier or already decoded frame starts over from the first
+        let reusable = open
+            .take()
+            .filter(|tiff| tiff.file == file && tiff.frame < frame);
+        let mut tiff = match reusable {
+            Some(tiff) => tiff,
+            None => OpenTiff {
+                file: file.to_path_buf(),
+                frame: 0,
+                decoder: open_tiff(file)?,
+            },
+        };
+        while tiff.frame < frame {
+            tiff.frame += 1;
+            tiff.decoder.next_image().with_context(|| {
+                format!(
+                    "Failed to read TIFF frame {} of {}",
+                    tiff.frame + 1,
+                    file.display()
+                )
+            })?;
+        }
+        let image = decode_tiff_frame(&mut tiff.decoder, file, frame)?;
+        *open = Some(tiff);
+        Ok(image)
+    }
+}
+
+impl PageSource for ImageSource {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn render_page(&self, page_num: usize, config: &Config) -> Result<RenderedPage> {
+        let page = page_num
+            .checked_sub(1)
+            .and_then(|idx| self.pages.get(idx))
+            .with_context(|| format!("Failed to get page {}", page_num))?;
+
+        let image = match page.frame {
+            Some(frame) => self.read_tiff_frame(&page.file, frame)?,
+            None => image::open(&page.file)
+                .with_context(|| format!("Failed to read image {}", page.file.display()))?,
+        };
+
+        // Images have no physical size, so oversized scans are scaled down instead
+        let pixels = image.width() as u64 * image.height() as u64;
+        if config.max_page_pixels == 0 || pixels <= config.max_page_pixels {
+            return Ok(RenderedPage::from_image(image));
+        }
+        let scale = (config.max_page_pixels as f64 / pixels as f64).sqrt();
+        let (width, height) = (
+            ((image.width() as f64 * scale) as u32).max(1),
+            ((image.height() as f64 * scale) as u32).max(1),
+        );
+        let mut rendered =
+            RenderedPage::from_image(image.resize(width, height, FilterType::Triangle));
+        rendered.warnings.push(format!(
+            "image scaled from {}x{} to {}x{} (--max-page-pixels {})",
+            image.width(),
+            image.height(),
+            width,
+            height,
+            config.max_page_pixels
+        ));
+        Ok(rendered)
+    }
+}
+
+fn image_pages(file: &Path) -> Result<Vec<ImagePage>> {
+    if !matches!(extension(file).as_deref(), Some("tif" | "tiff")) {
+        return Ok(vec![ImagePage {
+            file: file.to_path_buf(),
+            frame: None,
+        }]);
+    }
+
+    let frames = count_tiff_frames(file)?;
+    Ok((0..frames)
+        .map(|frame| ImagePage {
+            file: file.to_path_buf(),
+            frame: Some(frame),
+        })
+        .collect())
+}
+
+fn open_tiff(file: &Path) -> Result<TiffDecoder<BufReader<File>>> {
+    let reader = BufReader::new(
+        File::open(file).with_context(|| format!("Failed to open {}", file.display()))?,
+    );
+    TiffDecoder::new(reader).with_context(|| format!("Failed to read TIFF {}", file.display()))
+}
+
+fn count_tiff_frames(file: &Path) -> Result<usize> {
+    let mut decoder = open_tiff(file)?;
+    let mut frames = 1;
+    while decoder.more_images() {
+        decoder.next_image().with_context(|| {
+            format!(
+                "Failed to read TIFF frame {} of {}",
+                frames + 1,
+                file.display()
+            )
+        })?;
+        frames += 1;
+    }
+    Ok(frames)
+}
+
+/// Decode the frame `decoder` is at; `file` and `frame` are for error messages.
+fn decode_tiff_frame(
+    decoder: &mut TiffDecoder<BufReader<File>>,
+    file: &Path,
+    frame: usize,
+) -> Result<DynamicImage> {
+    let (width, height) = decoder.dimensions()?;
+    let color_type = decoder.colortype()?;
+    let data = decoder.read_image().with_context(|| {
+        format!(
+            "Failed to decode TIFF frame {} of {}",
+            frame + 1,
+            file.display()
+        )
+    })?;
+
+    let image = match (color_type, data) {
+        (TiffColorType::Gray(1), DecodingResult::U8(data)) => {
+            DynamicImage::ImageLuma8(unpack_bilevel(width, height, &data))
+        }
+        (TiffColorType::Gray(8), DecodingResult::U8(data)) => {
+            ImageBuffer::<Luma<u8>, _>::from_raw(width, height, data)
+                .map(DynamicImage::ImageLuma8)
+                .context("Invalid TIFF frame size")?
+        }
+        (TiffColorType::GrayA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::<LumaA<u8>, _>::from_raw(width, height, data)
+                .map(DynamicImage::ImageLumaA8)
+                .context("Invalid TIFF frame size")?
+        }
+        (TiffColorType::RGB(8), DecodingResult::U8(data)) => {
+            ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, data)
+                .map(DynamicImage::ImageRgb8)
+                .context("Invalid TIFF frame size")?
+        }
+        (TiffColorType::RGBA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, data)
+                .map(DynamicImage::ImageRgba8)
+                .context("Invalid TIFF frame size")?
+        }
+        (TiffColorType::CMYK(8), DecodingResult::U8(data)) => {
+            let rgb = data
+                .chunks_exact(4)
+                .flat_map(|p| {
+                    let k = 255 - p[3] as u16;
+                    [p[0], p[1], p[2]].map(|c| ((255 - c as u16) * k / 255) as u8)
+                })
+                .collect();
+            ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, rgb)
+                .map(DynamicImage::ImageRgb8)
+                .context("Invalid TIFF frame size")?
+        }
+        (TiffColorType::Gray(16), DecodingResult::U16(data)) => {
+            ImageBuffer::<Luma<u16>, _>::from_raw(width, height, data)
+                .map(DynamicImage::ImageLuma16)
+                .context("Invalid TIFF frame size")?
+        }
+        (TiffColorType::RGB(16), DecodingResult::U16(data)) => {
+            ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, data)
+                .map(DynamicImage::ImageRgb16)
+                .context("Invalid TIFF frame size")?
+        }
+        (TiffColorType::RGBA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, data)
+                .map(DynamicImage::ImageRgba16)
+                .context("Invalid TIFF frame size")?
+        }
+        (color_type, _) => anyhow::bail!(
+            "Unsupported TIFF color type {:?} in {}",
+            color_type,
+            file.display()
+        ),
+    };
+    Ok(image)
+}
+
+/// Expand 1-bit rows (padded to whole bytes) to 8-bit grayscale.
+fn unpack_bilevel(width: u32, height: u32, data: &[u8]) -> GrayImage {
+    let stride = width.div_ceil(8) as usize;
+    GrayImage::from_fn(width, height, |x, y| {
+        let byte = data
+            .get(y as usize * stride + x as usize / 8)
+            .copied()
+            .unwrap_or(0xff);
+        let bit = (byte >> (7 - (x % 8))) & 1;
+        Luma([if bit == 1 { 255 } else { 0 }])
+    })
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+}
+
+/// Compare strings treating runs of digits as numbers.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                let (xt, yt) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = xt.len().cmp(&yt.len()).then_with(|| xt.cmp(yt));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_numbers_by_value() {
+        assert_eq!(natural_cmp("page2.png", "page10.png"), Ordering::Less);
+        assert_eq!(natural_cmp("page10.png", "page9.png"), Ordering::Greater);
+        assert_eq!(natural_cmp("page002.png", "page2.png"), Ordering::Equal);
+        assert_eq!(natural_cmp("page2.png", "page2a.png"), Ordering::Less);
+        assert_eq!(natural_cmp("a10", "b2"), Ordering::Less);
+        assert_eq!(natural_cmp("scan", "scan1"), Ordering::Less);
+    }
+
+    #[test]
+    fn sorts_scan_directories_naturally() {
+        let mut names = vec!["p10.tif", "p1.tif", "p2.tif", "cover.tif", "p1b.tif"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec!["cover.tif", "p1.tif", "p1b.tif", "p2.tif", "p10.tif"]
+        );
+    }
+
+    #[test]
+    fn unpacks_padded_bilevel_rows() {
+        // 10 pixels wide: two bytes per row, the last 6 bits are padding
+        let data = [0b1010_1010, 0b0100_0000, 0b0000_0000, 0b1111_1111];
+        let image = unpack_bilevel(10, 2, &data);
+        let row = |y| {
+            (0..10)
+                .map(|x| image.get_pixel(x, y)[0])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(row(0), vec![255, 0, 255, 0, 255, 0, 255, 0, 0, 255]);
+        assert_eq!(row(1), vec![0, 0, 0, 0, 0, 0, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn treats_missing_bilevel_data_as_white() {
+        let image = unpack_bilevel(3, 2, &[0b0000_0000]);
+        assert_eq!(image.get_pixel(0, 0)[0], 0);
+        assert_eq!(image.get_pixel(2, 1)[0], 255);
+    }
+}
@@ -1,14 +1,18 @@
-use crate::cache::CacheManager;
+use crate::annotations::{self, PageAnnotation, PageLink};
 use crate::config::Config;
-use crate::image_processor;
-use crate::ocr_engine::{OcrBlock, OcrEngine, OcrPage};
+use crate::error::OcrError;
+use crate::ocr_engine::{BoundingBox, OcrBlock};
+use crate::outline::{self, OutlineEntry};
+use crate::page_source::{self, PageSource, RenderedPage};
 use anyhow::{Context, Result};
 use image::{DynamicImage, RgbaImage};
-use indicatif::ProgressBar;
 use pdfium_render::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::OnceLock;
+
+/// Environment variable consulted for the PDF password when no CLI option is given.
+pub const PASSWORD_ENV: &str = "RUST_OCR2MD_PDF_PASSWORD";
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PageResult {
@@ -17,169 +21,278 @@ pub struct PageResult {
     pub detected_language: Option<String>,
     pub image_width: u32,
     pub image_height: u32,
+    #[serde(default)]
+    pub links: Vec<PageLink>,
+    #[serde(default)]
+    pub annotations: Vec<PageAnnotation>,
+}
+
+/// Fields from the PDF info dictionary.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DocumentInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    pub creator: Option<String>,
+    pub producer: Option<String>,
+    pub created: Option<String>,
+    pub modified: Option<String>,
 }
 
 pub struct PdfProcessor {
     pdf_path: PathBuf,
-    dpi: u32,
+    /// A PDF read from stdin or memory, kept open; a file is opened again for each page
+    memory: Option<PdfDocument<'static>>,
+    password: Option<String>,
     page_count: usize,
+    outline: Vec<OutlineEntry>,
+    info: DocumentInfo,
+    page_labels: Vec<Option<String>>,
 }
 
 impl PdfProcessor {
-    pub fn new<P: AsRef<Path>>(pdf_path: P, dpi: u32) -> Result<Self> {
-        let pdf_path = pdf_path.as_ref().to_path_buf();
+    pub fn new<P: AsRef<Path>>(pdf_path: P, password: Option<String>) -> Result<Self> {
+        Self::open(pdf_path.as_ref().to_path_buf(), None, password)
+    }
 
-        let pdfium = Pdfium::new(
-            Pdfium::bind_to_system_library()
-                .or_else(|_| Pdfium::bind_to_library("pdfium"))
-                .context("Failed to load PDFium library. Please ensure PDFium is installed.")?,
-        );
+    /// A PDF held in memory, e.g. read from stdin; `name` is used for display and cache keys.
+    pub fn from_bytes(bytes: Vec<u8>, name: &Path, password: Option<String>) -> Result<Self> {
+        let loaded = pdfium()?.load_pdf_from_byte_vec(bytes, password.as_deref());
+        let document = loaded_document(loaded, name, password.is_some())?;
+        Self::open(name.to_path_buf(), Some(document), password)
+    }
 
-        let document = pdfium
-            .load_pdf_from_file(&pdf_path, None)
-            .context("Failed to load PDF file")?;
+    fn open(
+        pdf_path: PathBuf,
+        memory: Option<PdfDocument<'static>>,
+        password: Option<String>,
+    ) -> Result<Self> {
+        let mut processor = Self {
+            pdf_path,
+            memory,
+            password,
+            page_count: 0,
+            outline: Vec::new(),
+            info: DocumentInfo::default(),
+            page_labels: Vec::new(),
+        };
+        let (page_count, outline, info, page_labels) = processor.with_document(|document| {
+            let page_labels: Vec<Option<String>> = document
+                .pages()
+                .iter()
+                .map(|page| page.label().map(str::to_string))
+                .collect();
+            Ok((
+                document.pages().len() as usize,
+                outline::read_outline(document),
+                read_document_info(document),
+                page_labels,
+            ))
+        })?;
+        processor.page_count = page_count;
+        processor.outline = outline;
+        processor.info = info;
+        processor.page_labels = page_labels;
+        Ok(processor)
+    }
 
-        let page_count = document.pages().len() as usize;
+    /// Run `f` on the document: the one held in memory, or the file opened for this call.
+    fn with_document<T>(&self, f: impl FnOnce(&PdfDocument) -> Result<T>) -> Result<T> {
+        match &self.memory {
+            Some(document) => f(document),
+            None => {
+                let loaded = pdfium()?.load_pdf_from_file(&self.pdf_path, self.password.as_deref());
+                f(&loaded_document(
+                    loaded,
+                    &self.pdf_path,
+                    self.password.is_some(),
+                )?)
+            }
+        }
+    }
+}
 
-        Ok(Self {
-            pdf_path,
-            dpi,
-            page_count,
-        })
+impl PageSource for PdfProcessor {
+    fn path(&self) -> &Path {
+        &self.pdf_path
     }
 
-    pub fn page_count(&self) -> usize {
+    fn page_count(&self) -> usize {
         self.page_count
     }
 
-    pub fn process_pages(
-        &self,
-        page_range: &[usize],
-        ocr_engine: &OcrEngine,
-        config: &Config,
-        progress_bar: &ProgressBar,
-    ) -> Result<Vec<PageResult>> {
-        let results = Arc::new(Mutex::new(Vec::new()));
-        let errors = Arc::new(Mutex::new(Vec::new()));
-        let pdf_path = self.pdf_path.clone();
-        let dpi = self.dpi;
-        let cache = CacheManager::new(&config.cache)?;
-
-        for &page_num in page_range {
-            match process_single_page(&pdf_path, page_num, dpi, ocr_engine, config, &cache) {
-                Ok(result) => {
-                    results.lock().unwrap().push(result);
-                }
-                Err(e) => {
-                    errors.lock().unwrap().push((page_num, e.to_string()));
-                }
-            }
-            progress_bar.inc(1);
-        }
+    fn render_page(&self, page_num: usize, config: &Config) -> Result<RenderedPage> {
+        self.with_document(|document| {
+            let page = document
+                .pages()
+                .get((page_num - 1) as u16)
+                .context(format!("Failed to get page {}", page_num))?;
+            render(&page, page_num, config)
+        })
+    }
+
+    fn outline(&self) -> &[OutlineEntry] {
+        &self.outline
+    }
 
-        let mut final_results = results.lock().unwrap().clone();
-        final_results.sort_by_key(|r| r.page_num);
+    fn info(&self) -> DocumentInfo {
+        self.info.clone()
+    }
 
-        let errors = errors.lock().unwrap();
-        if !errors.is_empty() {
-            eprintln!("⚠️  Errors occurred on {} pages:", errors.len());
-            for (page, err) in errors.iter() {
-                eprintln!("  Page {}: {}", page, err);
-            }
-        }
+    fn page_labels(&self) -> &[Option<String>] {
+        &self.page_labels
+    }
 
-        Ok(final_results)
+    fn text_layer(&self, page_num: usize) -> Option<String> {
+        self.with_document(|document| {
+            let page = document.pages().get((page_num - 1) as u16)?;
+            let text = page.text()?.all();
+            Ok(text)
+        })
+        .ok()
     }
 }
 
-fn process_single_page(
-    pdf_path: &Path,
-    page_num: usize,
-    dpi: u32,
-    ocr_engine: &OcrEngine,
-    config: &Config,
-    cache: &CacheManager,
-) -> Result<PageResult> {
-    let pdfium = Pdfium::new(
-        Pdfium::bind_to_system_library()
-            .or_else(|_| Pdfium::bind_to_library("pdfium"))
-            .context("Failed to load PDFium library")?,
-    );
-
-    let document = pdfium
-        .load_pdf_from_file(pdf_path, None)
-        .context("Failed to load PDF file")?;
-
-    let page = document
-        .pages()
-        .get((page_num - 1) as u16)
-        .context(format!("Failed to get page {}", page_num))?;
+fn render(page: &PdfPage, page_num: usize, config: &Config) -> Result<RenderedPage> {
+    // Posters and other huge pages are rendered at a lower DPI to bound memory
+    let (width, height) = (page.width().value, page.height().value);
+    let dpi = page_source::capped_dpi(width, height, config.dpi, config.max_page_pixels);
+    let mut warnings = Vec::new();
+    if dpi < config.dpi {
+        warnings.push(format!(
+            "rendered at {} DPI instead of {} (--max-page-pixels {})",
+            dpi, config.dpi, config.max_page_pixels
+        ));
+    }
 
     let render_config = PdfRenderConfig::new()
-        .set_target_width((page.width().value * dpi as f32 / 72.0) as i32)
-        .set_maximum_height((page.height().value * dpi as f32 / 72.0) as i32);
+        .set_target_width((width * dpi as f32 / 72.0) as i32)
+        .set_maximum_height((height * dpi as f32 / 72.0) as i32);
 
     let bitmap = page
         .render_with_config(&render_config)
         .context(format!("Failed to render page {}", page_num))?;
 
     let image = bitmap_to_image(&bitmap)?;
-    let raw_bytes = image.to_rgba8().into_raw();
-    let config_fingerprint = format!(
-        "engine:{:?}|langs:{}|preprocess:{}|math:{}|layout:{}|gpu:{}",
-        config.engine, config.languages, config.preprocess, config.math_ocr, config.layout, config.use_gpu
-    );
-    let page_hash = if cache.enabled() {
-        Some(cache.make_hash(
-            pdf_path,
-            page_num,
-            dpi,
-            &config_fingerprint,
-            &raw_bytes,
-        ))
+    let scale = image.width() as f32 / page.width().value;
+    let image_regions = if config.figures {
+        page_image_regions(page, scale)
     } else {
-        None
+        Vec::new()
+    };
+    let (links, annotations) = if config.annotations {
+        (
+            annotations::read_links(page, scale),
+            annotations::read_annotations(page, scale),
+        )
+    } else {
+        (Vec::new(), Vec::new())
     };
 
-    if let Some(ref hash) = page_hash {
-        if let Some(cached) = cache.load_page(hash)? {
-            return Ok(cached);
-        }
+    Ok(RenderedPage {
+        image,
+        image_regions,
+        links,
+        annotations,
+        warnings,
+    })
+}
+
+/// PDFium, bound on first use and shared by every document.
+fn pdfium() -> Result<&'static Pdfium> {
+    static PDFIUM: OnceLock<Pdfium> = OnceLock::new();
+    if let Some(pdfium) = PDFIUM.get() {
+        return Ok(pdfium);
     }
+    let bindings = Pdfium::bind_to_system_library()
+        .or_else(|_| Pdfium::bind_to_library("pdfium"))
+        .context("Failed to load PDFium library. Please ensure PDFium is installed.")?;
+    Ok(PDFIUM.get_or_init(|| Pdfium::new(bindings)))
+}
 
-    let processed_image = if config.preprocess {
-        if let (Some(hash), true) = (page_hash.as_ref(), cache.enabled()) {
-            if let Some(img) = cache.load_preprocessed(hash)? {
-                img
+/// Report encrypted files separately from generic load failures.
+fn loaded_document<'a>(
+    loaded: Result<PdfDocument<'a>, PdfiumError>,
+    pdf_path: &Path,
+    has_password: bool,
+) -> Result<PdfDocument<'a>> {
+    match loaded {
+        Ok(document) => Ok(document),
+        Err(PdfiumError::PdfiumLibraryInternalError(PdfiumInternalError::PasswordError)) => {
+            let message = if has_password {
+                format!("incorrect password for {}", pdf_path.display())
             } else {
-                let img = image_processor::preprocess_image(image.clone(), config.use_gpu)?;
-                cache.store_preprocessed(hash, &img)?;
-                img
-            }
-        } else {
-            image_processor::preprocess_image(image.clone(), config.use_gpu)?
+                format!(
+                    "{} is password-protected. Provide --password, --password-file or set {}",
+                    pdf_path.display(),
+                    PASSWORD_ENV
+                )
+            };
+            Err(OcrError::EncryptedPdf(message).into())
         }
-    } else {
-        image.clone()
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to load PDF file")),
+    }
+}
+
+fn read_document_info(document: &PdfDocument) -> DocumentInfo {
+    let metadata = document.metadata();
+    let get = |tag: PdfDocumentMetadataTagType| {
+        metadata
+            .get(tag)
+            .map(|t| t.value().trim().to_string())
+            .filter(|v| !v.is_empty())
     };
 
-    let ocr_page: OcrPage = ocr_engine
-        .recognize(&processed_image, config)
-        .context(format!("OCR failed on page {}", page_num))?;
+    DocumentInfo {
+        title: get(PdfDocumentMetadataTagType::Title),
+        author: get(PdfDocumentMetadataTagType::Author),
+        subject: get(PdfDocumentMetadataTagType::Subject),
+        keywords: get(PdfDocumentMetadataTagType::Keywords),
+        creator: get(PdfDocumentMetadataTagType::Creator),
+        producer: get(PdfDocumentMetadataTagType::Producer),
+        created: get(PdfDocumentMetadataTagType::CreationDate).map(|d| format_pdf_date(&d)),
+        modified: get(PdfDocumentMetadataTagType::ModificationDate).map(|d| format_pdf_date(&d)),
+    }
+}
 
-    let result = PageResult {
-        page_num,
-        blocks: ocr_page.blocks,
-        detected_language: ocr_page.detected_language,
-        image_width: processed_image.width(),
-        image_height: processed_image.height(),
-    };
+/// Format a PDF date string ("D:YYYYMMDDHHmmSS+HH'mm'") for display. Unparseable values pass through.
+fn format_pdf_date(raw: &str) -> String {
+    let value = raw.strip_prefix("D:").unwrap_or(raw);
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.len() < 8 {
+        return raw.to_string();
+    }
 
-    if let Some(hash) = page_hash {
-        cache.store_page(&hash, &result)?;
+    let part = |start: usize| digits.get(start..start + 2).unwrap_or("00");
+    let mut formatted = format!("{}-{}-{}", &digits[0..4], &digits[4..6], &digits[6..8]);
+    if digits.len() >= 12 {
+        formatted.push_str(&format!(" {}:{}:{}", part(8), part(10), part(12)));
     }
 
-    Ok(result)
+    let zone = &value[digits.len()..];
+    if zone.starts_with('Z') {
+        formatted.push_str(" UTC");
+    } else if let Some(sign) = zone.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let zone_digits: String = zone.chars().filter(|c| c.is_ascii_digit()).collect();
+        if zone_digits.len() >= 2 {
+            let minutes = zone_digits.get(2..4).unwrap_or("00");
+            formatted.push_str(&format!(" {}{}:{}", sign, &zone_digits[0..2], minutes));
+        }
+    }
+    formatted
+}
+
+/// Bounding boxes of embedded image objects, converted from PDF points to pixels.
+fn page_image_regions(page: &PdfPage, scale: f32) -> Vec<BoundingBox> {
+    let page_height = page.height().value;
+    page.objects()
+        .iter()
+        .filter(|object| object.object_type() == PdfPageObjectType::Image)
+        .filter_map(|object| object.bounds().ok())
+        .map(|bounds| annotations::pdf_rect_to_bbox(&bounds.to_rect(), page_height, scale))
+        .filter(|bbox| bbox.width > 0 && bbox.height > 0)
+        .collect()
 }
 
 fn bitmap_to_image(bitmap: &PdfBitmap) -> Result<DynamicImage> {
@@ -0,0 +1,76 @@
+use serde::Serialize;
+use std::time::Duration;
+
+/// Progress of a conversion, passed to a [`ProgressHandler`] as it happens.
+///
+/// Serialized as one JSON object tagged by `event`, which is what `--progress json` prints.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Event {
+    /// Human-readable note about what the converter is doing
+    Info {
+        message: String,
+    },
+    DocumentStarted {
+        input: String,
+        pages: usize,
+        /// Pages taken over from an earlier run and not converted again
+        resumed: usize,
+    },
+    PageStarted {
+        page: usize,
+    },
+    CacheHit {
+        page: usize,
+    },
+    PageFinished {
+        page: usize,
+        blocks: usize,
+        cache_hit: bool,
+        elapsed_ms: u64,
+    },
+    PageError {
+        page: usize,
+        error: String,
+        elapsed_ms: u64,
+    },
+    /// Conversion was cancelled; `remaining` pages were not started
+    Interrupted {
+        remaining: usize,
+    },
+    DocumentFinished {
+        input: String,
+        output: String,
+        pages: usize,
+        converted: usize,
+        elapsed_ms: u64,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Receives progress events from a [`Converter`](crate::Converter).
+///
+/// Events arrive on the converting thread, in order. Any `Fn(&Event)` closure is a handler.
+pub trait ProgressHandler: Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+impl<F: Fn(&Event) + Send + Sync> ProgressHandler for F {
+    fn on_event(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Handler that ignores every event; the default of a new converter.
+pub struct NoProgress;
+
+impl ProgressHandler for NoProgress {
+    fn on_event(&self, _event: &Event) {}
+}
+
+pub fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
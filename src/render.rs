@@ -0,0 +1,253 @@
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use rust_ocr2md::annotations;
+use rust_ocr2md::config::{self, ErrorPolicy, LoadedConfig};
+use rust_ocr2md::saved::SavedDocument;
+
+use crate::{is_stdout, MarkdownArgs};
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// Results saved with --json, cached page results (<cache dir>/ocr/*.json), or directories of them
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Output file, or - for stdout (default: the first input with the format's extension)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Output format (default: from the output file's extension, otherwise markdown)
+    #[arg(long, value_enum)]
+    to: Option<Format>,
+
+    /// Link prefix for figures as seen from the output file (default: the configured assets_dir)
+    #[arg(long)]
+    assets_link: Option<String>,
+
+    /// Config file to use instead of the discovered user/project files
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Named profile from the config files or built in: paper, book, scan-cjk
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Sort each page's blocks into reading order again (default: true)
+    #[arg(long)]
+    layout: Option<bool>,
+
+    /// Failed pages recorded in the results: skip, or placeholder comments (default: placeholder)
+    #[arg(long, value_enum)]
+    on_error: Option<ErrorPolicy>,
+
+    #[command(flatten)]
+    markdown: MarkdownArgs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Markdown,
+    /// Single HTML page; formulas are rendered with KaTeX loaded from a CDN, so they need
+    /// network access when the page is opened
+    Html,
+    /// The results again, merged into one saved document
+    Json,
+    /// Plain text, pages separated by form feeds
+    Text,
+}
+
+impl Format {
+    fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "md" | "markdown" => Some(Format::Markdown),
+            "html" | "htm" => Some(Format::Html),
+            "json" => Some(Format::Json),
+            "txt" => Some(Format::Text),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Html => "html",
+            Format::Json => "json",
+            Format::Text => "txt",
+        }
+    }
+}
+
+pub fn run(args: &RenderArgs) -> Result<()> {
+    let start = Instant::now();
+    let mut loaded = config::load(args.config.as_deref(), args.profile.as_deref())?;
+    let LoadedConfig {
+        config, explicit, ..
+    } = &mut loaded;
+    crate::set(&mut config.layout, &args.layout, "layout", explicit);
+    crate::set(&mut config.on_error, &args.on_error, "on_error", explicit);
+    args.markdown.apply(config, explicit);
+    crate::print_config_sources(&loaded);
+    let config = &loaded.config;
+
+    let documents = args
+        .inputs
+        .iter()
+        .map(|path| SavedDocument::load(path))
+        .collect::<Result<Vec<_>>>()?;
+    let document = SavedDocument::merge(documents)?;
+
+    let format = args
+        .to
+        .or_else(|| args.output.as_deref().and_then(Format::from_extension))
+        .unwrap_or(Format::Markdown);
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| args.inputs[0].with_extension(format.extension()));
+    if args.inputs.contains(&output) {
+        anyhow::bail!(
+            "{} would overwrite an input; choose another file with -o",
+            output.display()
+        );
+    }
+
+    let assets_link = args
+        .assets_link
+        .clone()
+        .unwrap_or_else(|| config.assets_dir.display().to_string());
+    let contents = match format {
+        Format::Markdown => document.markdown(config, &assets_link)?,
+        Format::Html => {
+            let title = document.info.title.clone().unwrap_or_else(|| {
+                document
+                    .input
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            });
+            to_html(&document.markdown(config, &assets_link)?, &title)
+        }
+        Format::Json => serde_json::to_string_pretty(&document)?,
+        Format::Text => document.text(config),
+    };
+
+    if is_stdout(&output) {
+        print!("{}", contents);
+    } else {
+        std::fs::write(&output, &contents)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        eprintln!(
+            "✅ Rendered {} pages → {} ({:.2}s)",
+            document.pages.len(),
+            output.display(),
+            start.elapsed().as_secs_f64()
+        );
+    }
+    Ok(())
+}
+
+const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/katex@0.16/dist/katex.min.css">
+<script defer src="https://cdn.jsdelivr.net/npm/katex@0.16/dist/katex.min.js"></script>
+<script>
+document.addEventListener("DOMContentLoaded", () => {
+  for (const el of document.querySelectorAll(".math")) {
+    katex.render(el.textContent, el, {
+      displayMode: el.classList.contains("math-display"),
+      throwOnError: false,
+    });
+  }
+});
+</script>
+<style>
+body { max-width: 50em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.6; }
+img { max-width: 100%; }
+</style>
+</head>
+<body>
+{body}</body>
+</html>
+"#;
+
+/// An HTML page; `$...$` and `$$...$$` become KaTeX math.
+///
+/// Text, comments and link targets come from the PDF, so raw HTML other than the page
+/// anchors and comments the Markdown builder writes is shown as text, and only relative,
+/// http(s) and mailto links are kept.
+fn to_html(markdown: &str, title: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_MATH;
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => raw_html(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        other => other,
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, events);
+    let title = title
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    HTML_TEMPLATE
+        .replace("{title}", &title)
+        .replace("{body}", &body)
+}
+
+/// Page anchors and whole comments pass through; any other HTML becomes text.
+fn raw_html(html: CowStr<'_>) -> Event<'_> {
+    let trimmed = html.trim();
+    let is_comment = trimmed.len() >= 7
+        && trimmed.starts_with("<!--")
+        && trimmed.ends_with("-->")
+        && !trimmed[4..trimmed.len() - 3].contains("--");
+    let is_anchor = trimmed
+        .strip_prefix("<a id=\"page-")
+        .and_then(|rest| rest.strip_suffix("\"></a>"))
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    if is_comment || is_anchor {
+        Event::Html(html)
+    } else {
+        Event::Text(html)
+    }
+}
+
+/// `url` if it is relative or http, https or mailto; otherwise an empty link.
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    if annotations::is_safe_uri(&url) {
+        url
+    } else {
+        CowStr::Borrowed("")
+    }
+}
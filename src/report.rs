@@ -0,0 +1,321 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::{PageFailure, Stage};
+use crate::ocr_engine::BlockType;
+use crate::pdf_processor::PageResult;
+use crate::progress;
+
+/// Pages whose mean block confidence falls below this get a warning.
+const LOW_CONFIDENCE: f32 = 0.6;
+/// Number of slowest pages listed in a document summary.
+const SLOWEST_PAGES: usize = 5;
+
+/// Wall-clock time spent in each pipeline stage of one page, in milliseconds.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PageTimings {
+    pub render_ms: u64,
+    pub preprocess_ms: u64,
+    /// Text detection and recognition, excluding formula recognition
+    pub ocr_ms: u64,
+    pub math_ms: u64,
+    pub figures_ms: u64,
+    pub total_ms: u64,
+}
+
+impl PageTimings {
+    fn add(&mut self, other: &PageTimings) {
+        self.render_ms += other.render_ms;
+        self.preprocess_ms += other.preprocess_ms;
+        self.ocr_ms += other.ocr_ms;
+        self.math_ms += other.math_ms;
+        self.figures_ms += other.figures_ms;
+        self.total_ms += other.total_ms;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BlockCounts {
+    pub total: usize,
+    pub text: usize,
+    pub formula: usize,
+    pub table: usize,
+    pub figure: usize,
+    pub unknown: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Partial,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PageReport {
+    pub page: usize,
+    pub status: Status,
+    pub cache_hit: bool,
+    pub timings: PageTimings,
+    pub blocks: BlockCounts,
+    /// Over recognized blocks; figures are excluded
+    pub mean_confidence: Option<f32>,
+    pub min_confidence: Option<f32>,
+    pub detected_language: Option<String>,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+    /// Pipeline stage the page failed in
+    pub failed_stage: Option<Stage>,
+}
+
+impl PageReport {
+    pub fn success(
+        result: &PageResult,
+        cache_hit: bool,
+        timings: PageTimings,
+        mut warnings: Vec<String>,
+    ) -> Self {
+        let mut blocks = BlockCounts {
+            total: result.blocks.len(),
+            ..Default::default()
+        };
+        for block in &result.blocks {
+            match block.block_type {
+                BlockType::Text => blocks.text += 1,
+                BlockType::Formula => blocks.formula += 1,
+                BlockType::Table => blocks.table += 1,
+                BlockType::Figure => blocks.figure += 1,
+                BlockType::Unknown => blocks.unknown += 1,
+            }
+        }
+
+        let confidences: Vec<f32> = result
+            .blocks
+            .iter()
+            .filter(|b| b.block_type != BlockType::Figure)
+            .map(|b| b.confidence)
+            .collect();
+        let mean_confidence = (!confidences.is_empty())
+            .then(|| confidences.iter().sum::<f32>() / confidences.len() as f32);
+        let min_confidence = confidences.iter().copied().reduce(f32::min);
+
+        if !result.blocks.iter().any(|b| !b.text.trim().is_empty()) {
+            warnings.push("no text recognized".to_string());
+        }
+        if let Some(mean) = mean_confidence.filter(|m| *m < LOW_CONFIDENCE) {
+            warnings.push(format!("low mean confidence {:.2}", mean));
+        }
+
+        Self {
+            page: result.page_num,
+            status: Status::Ok,
+            cache_hit,
+            timings,
+            blocks,
+            mean_confidence,
+            min_confidence,
+            detected_language: result.detected_language.clone(),
+            warnings,
+            error: None,
+            failed_stage: None,
+        }
+    }
+
+    pub fn failure(failure: &PageFailure, timings: PageTimings) -> Self {
+        Self {
+            page: failure.page,
+            status: Status::Failed,
+            cache_hit: false,
+            timings,
+            blocks: BlockCounts::default(),
+            mean_confidence: None,
+            min_confidence: None,
+            detected_language: None,
+            warnings: Vec::new(),
+            error: Some(failure.message.clone()),
+            failed_stage: Some(failure.stage),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    pub pages: usize,
+    pub converted: usize,
+    pub failed: usize,
+    pub cache_hits: usize,
+    pub warnings: usize,
+    pub blocks: usize,
+    /// Mean of the page means
+    pub mean_confidence: Option<f32>,
+    /// Summed over pages
+    pub timings: PageTimings,
+}
+
+impl Summary {
+    fn from_pages(pages: &[PageReport]) -> Self {
+        let mut summary = Summary {
+            pages: pages.len(),
+            ..Default::default()
+        };
+        let mut confidences = Vec::new();
+        for page in pages {
+            match page.status {
+                Status::Failed => summary.failed += 1,
+                _ => summary.converted += 1,
+            }
+            summary.cache_hits += page.cache_hit as usize;
+            summary.warnings += page.warnings.len();
+            summary.blocks += page.blocks.total;
+            summary.timings.add(&page.timings);
+            confidences.extend(page.mean_confidence);
+        }
+        summary.mean_confidence = (!confidences.is_empty())
+            .then(|| confidences.iter().sum::<f32>() / confidences.len() as f32);
+        summary
+    }
+
+    fn add(&mut self, other: &Summary) {
+        // Confidence is re-averaged by converted page count
+        let total = self.converted + other.converted;
+        self.mean_confidence = match (self.mean_confidence, other.mean_confidence) {
+            (Some(a), Some(b)) if total > 0 => {
+                Some((a * self.converted as f32 + b * other.converted as f32) / total as f32)
+            }
+            (a, b) => a.or(b),
+        };
+        self.pages += other.pages;
+        self.converted += other.converted;
+        self.failed += other.failed;
+        self.cache_hits += other.cache_hits;
+        self.warnings += other.warnings;
+        self.blocks += other.blocks;
+        self.timings.add(&other.timings);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentReport {
+    pub input: String,
+    pub output: Option<String>,
+    pub status: Status,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+    pub summary: Summary,
+    /// Slowest pages first
+    pub slowest_pages: Vec<usize>,
+    pub failed_pages: Vec<usize>,
+    pub warning_pages: Vec<usize>,
+    pub pages: Vec<PageReport>,
+}
+
+impl DocumentReport {
+    /// `output` is `None` for a document converted in memory only.
+    pub fn new(
+        input: &Path,
+        output: Option<&Path>,
+        mut pages: Vec<PageReport>,
+        elapsed: Duration,
+    ) -> Self {
+        pages.sort_by_key(|p| p.page);
+        let summary = Summary::from_pages(&pages);
+
+        let mut by_time: Vec<&PageReport> = pages.iter().collect();
+        by_time.sort_by_key(|p| std::cmp::Reverse(p.timings.total_ms));
+        let slowest_pages = by_time.iter().take(SLOWEST_PAGES).map(|p| p.page).collect();
+
+        let status = match (summary.converted, summary.failed) {
+            (0, _) => Status::Failed,
+            (_, 0) => Status::Ok,
+            _ => Status::Partial,
+        };
+
+        Self {
+            input: input.display().to_string(),
+            output: output.map(|p| p.display().to_string()),
+            status,
+            error: None,
+            elapsed_ms: progress::millis(elapsed),
+            summary,
+            slowest_pages,
+            failed_pages: pages
+                .iter()
+                .filter(|p| p.status == Status::Failed)
+                .map(|p| p.page)
+                .collect(),
+            warning_pages: pages
+                .iter()
+                .filter(|p| !p.warnings.is_empty())
+                .map(|p| p.page)
+                .collect(),
+            pages,
+        }
+    }
+
+    /// A document that was not converted at all.
+    pub fn not_converted(
+        input: &Path,
+        status: Status,
+        error: Option<String>,
+        elapsed: Duration,
+    ) -> Self {
+        Self {
+            input: input.display().to_string(),
+            output: None,
+            status,
+            error,
+            elapsed_ms: progress::millis(elapsed),
+            summary: Summary::default(),
+            slowest_pages: Vec::new(),
+            failed_pages: Vec::new(),
+            warning_pages: Vec::new(),
+            pages: Vec::new(),
+        }
+    }
+}
+
+/// Everything `--report` writes: one entry per document plus run-wide totals.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    pub tool: String,
+    pub generated: String,
+    pub elapsed_ms: u64,
+    pub documents_failed: usize,
+    pub summary: Summary,
+    pub documents: Vec<DocumentReport>,
+}
+
+impl RunReport {
+    pub fn new(documents: Vec<DocumentReport>, elapsed: Duration) -> Self {
+        let mut summary = Summary::default();
+        for document in &documents {
+            summary.add(&document.summary);
+        }
+
+        Self {
+            tool: format!("RustOCR2md v{}", env!("CARGO_PKG_VERSION")),
+            generated: Local::now().to_rfc3339(),
+            elapsed_ms: progress::millis(elapsed),
+            documents_failed: documents
+                .iter()
+                .filter(|d| d.status == Status::Failed)
+                .count(),
+            summary,
+            documents,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write report {}", path.display()))
+    }
+}
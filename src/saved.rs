@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, ErrorPolicy};
+use crate::error::PageFailure;
+use crate::layout;
+use crate::markdown_builder::MarkdownBuilder;
+use crate::outline::OutlineEntry;
+use crate::pdf_processor::{DocumentInfo, PageResult};
+
+/// OCR results of one document with everything else the Markdown is built from, so it
+/// can be built again with other options without running OCR.
+///
+/// Written by [`ConvertOptions::json`](crate::ConvertOptions::json) and read by `render`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedDocument {
+    pub tool: String,
+    pub input: PathBuf,
+    #[serde(default)]
+    pub info: DocumentInfo,
+    #[serde(default)]
+    pub outline: Vec<OutlineEntry>,
+    /// Page labels indexed by page number - 1
+    #[serde(default)]
+    pub page_labels: Vec<Option<String>>,
+    /// Pages that converted, in page order
+    pub pages: Vec<PageResult>,
+    #[serde(default)]
+    pub failures: Vec<PageFailure>,
+}
+
+impl SavedDocument {
+    pub fn new(input: &Path, pages: Vec<PageResult>) -> Self {
+        Self {
+            tool: format!("RustOCR2md v{}", env!("CARGO_PKG_VERSION")),
+            input: input.to_path_buf(),
+            info: DocumentInfo::default(),
+            outline: Vec::new(),
+            page_labels: Vec::new(),
+            pages,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Read a saved document, a cached `PageResult` file, or a directory of them.
+    ///
+    /// Cached pages carry no document information, so only the pages are filled in and
+    /// `input` is the path that was read.
+    pub fn load(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            let mut pages = Vec::new();
+            let entries = std::fs::read_dir(path)
+                .with_context(|| format!("Failed to read directory {}", path.display()))?;
+            for entry in entries {
+                let file = entry?.path();
+                if file.extension().is_some_and(|ext| ext == "json") {
+                    pages.push(read_page(&file)?);
+                }
+            }
+            return Self::from_pages(path, pages);
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let value: serde_json::Value = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+        if value.get("pages").is_some() {
+            serde_json::from_value(value)
+                .with_context(|| format!("Invalid saved document {}", path.display()))
+        } else {
+            let page = serde_json::from_value(value).with_context(|| {
+                format!("Not a saved document or page result: {}", path.display())
+            })?;
+            Self::from_pages(path, vec![page])
+        }
+    }
+
+    /// Combine several loaded files into one document; the first file's information is kept.
+    pub fn merge(documents: Vec<Self>) -> Result<Self> {
+        let mut documents = documents.into_iter();
+        let Some(mut merged) = documents.next() else {
+            anyhow::bail!("No page results to render");
+        };
+        for document in documents {
+            merged.pages.extend(document.pages);
+            merged.failures.extend(document.failures);
+        }
+        sort_pages(&mut merged.pages)?;
+        merged.failures.sort_by_key(|f| f.page);
+        Ok(merged)
+    }
+
+    fn from_pages(input: &Path, mut pages: Vec<PageResult>) -> Result<Self> {
+        sort_pages(&mut pages)?;
+        Ok(Self::new(input, pages))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Build the Markdown with the Markdown options of `config`.
+    ///
+    /// `layout` sorts every page's blocks into reading order again, `outline` and
+    /// `annotations` can only drop what was saved, and failed pages get placeholders
+    /// as set by `on_error`. `assets_link` is the figure link prefix.
+    pub fn markdown(&self, config: &Config, assets_link: &str) -> Result<String> {
+        let outline = if config.outline {
+            self.outline.clone()
+        } else {
+            Vec::new()
+        };
+        MarkdownBuilder::from_config(config)
+            .with_assets_link(assets_link)
+            .with_outline(outline)
+            .with_document_info(self.info.clone())
+            .with_page_labels(self.page_labels.clone())
+            .with_failed_pages(match config.on_error {
+                ErrorPolicy::Placeholder => self.failures.clone(),
+                ErrorPolicy::Skip | ErrorPolicy::Abort => Vec::new(),
+            })
+            .build(&self.pages_for(config), &self.input)
+    }
+
+    /// Plain text: blocks separated by blank lines, pages by a form feed.
+    pub fn text(&self, config: &Config) -> String {
+        self.pages_for(config)
+            .iter()
+            .map(|page| {
+                page.blocks
+                    .iter()
+                    .map(|block| block.text.trim())
+                    .filter(|text| !text.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\x0c\n")
+            + "\n"
+    }
+
+    fn pages_for(&self, config: &Config) -> Vec<PageResult> {
+        let mut pages = self.pages.clone();
+        for page in &mut pages {
+            if config.layout {
+                layout::sort_by_reading_order(&mut page.blocks);
+            }
+            if !config.annotations {
+                page.links.clear();
+                page.annotations.clear();
+            }
+        }
+        pages
+    }
+}
+
+/// Sort pages and reject page numbers that occur twice, which means the results come
+/// from more than one document.
+fn sort_pages(pages: &mut [PageResult]) -> Result<()> {
+    pages.sort_by_key(|p| p.page_num);
+    match pages.windows(2).find(|w| w[0].page_num == w[1].page_num) {
+        Some(pair) => anyhow::bail!(
+            "Page {} appears more than once; are these results from several documents?",
+            pair[0].page_num
+        ),
+        None => Ok(()),
+    }
+}
+
+fn read_page(path: &Path) -> Result<PageResult> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Invalid page result {}", path.display()))
+}
@@ -0,0 +1,493 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use rust_ocr2md::progress::{Event, ProgressHandler};
+use rust_ocr2md::{ConvertOptions, Converter, Document};
+
+use crate::PipelineArgs;
+
+/// Largest accepted upload.
+const MAX_UPLOAD_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    bind: String,
+
+    /// Jobs that may wait for the converter; further submissions get 503
+    #[arg(long, default_value = "16")]
+    queue_size: usize,
+
+    /// Finished jobs kept for fetching results; the oldest are dropped first
+    #[arg(long, default_value = "100")]
+    keep_jobs: usize,
+
+    #[command(flatten)]
+    pub(crate) pipeline: PipelineArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+struct Job {
+    name: String,
+    status: JobStatus,
+    /// Pages selected, known once the job is running
+    pages: Option<usize>,
+    converted: usize,
+    failed: usize,
+    started: Option<Instant>,
+    elapsed_ms: Option<u64>,
+    error: Option<String>,
+    document: Option<Document>,
+}
+
+/// A submitted PDF waiting for the converter.
+struct Submission {
+    id: u64,
+    /// The upload, kept on disk until the job starts
+    upload: PathBuf,
+    options: ConvertOptions,
+}
+
+/// Every known job by id, in submission order.
+struct Jobs {
+    table: Mutex<BTreeMap<u64, Job>>,
+    keep: usize,
+    next_id: AtomicU64,
+    /// Figures of job N are written to `assets_root/N`
+    assets_root: PathBuf,
+    /// Uploads waiting in the queue
+    upload_dir: PathBuf,
+    uploads: AtomicU64,
+}
+
+impl Jobs {
+    fn update(&self, id: u64, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.table.lock().unwrap().get_mut(&id) {
+            f(job);
+        }
+    }
+
+    /// Drop the oldest finished jobs beyond the retention limit.
+    fn evict(&self, table: &mut BTreeMap<u64, Job>) {
+        let finished: Vec<u64> = table
+            .iter()
+            .filter(|(_, job)| matches!(job.status, JobStatus::Done | JobStatus::Failed))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(self.keep))
+        {
+            table.remove(id);
+            self.remove_assets(*id);
+        }
+    }
+
+    fn remove_assets(&self, id: u64) {
+        let dir = self.assets_root.join(id.to_string());
+        match std::fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                eprintln!("⚠️  Failed to remove {}: {}", dir.display(), e)
+            }
+            _ => {}
+        }
+    }
+
+    fn status(&self, id: u64) -> Option<serde_json::Value> {
+        let table = self.table.lock().unwrap();
+        let job = table.get(&id)?;
+        let queue_position = (job.status == JobStatus::Queued).then(|| {
+            table
+                .range(..id)
+                .filter(|(_, other)| other.status == JobStatus::Queued)
+                .count()
+        });
+        Some(json!({
+            "id": id.to_string(),
+            "name": job.name,
+            "status": job.status,
+            "queue_position": queue_position,
+            "pages": job.pages,
+            "converted": job.converted,
+            "failed": job.failed,
+            "elapsed_ms": job.elapsed_ms.or(job.started.map(|s| s.elapsed().as_millis() as u64)),
+            "error": job.error,
+        }))
+    }
+}
+
+/// Counts page progress against whichever job is running.
+struct JobProgress(Arc<Jobs>);
+
+impl ProgressHandler for JobProgress {
+    fn on_event(&self, event: &Event) {
+        let mut table = self.0.table.lock().unwrap();
+        let Some(job) = table
+            .values_mut()
+            .find(|job| job.status == JobStatus::Running)
+        else {
+            return;
+        };
+        match event {
+            Event::DocumentStarted { pages, .. } => job.pages = Some(*pages),
+            Event::PageFinished { .. } => job.converted += 1,
+            Event::PageError { .. } => job.failed += 1,
+            _ => {}
+        }
+    }
+}
+
+pub fn run(args: &ServeArgs) -> Result<()> {
+    eprintln!("🚀 RustOCR2md - High-performance PDF OCR Tool");
+    eprintln!("================================================");
+
+    let loaded = args.pipeline.load_config()?;
+    crate::print_config_sources(&loaded);
+    let jobs = Arc::new(Jobs {
+        table: Mutex::new(BTreeMap::new()),
+        keep: args.keep_jobs,
+        next_id: AtomicU64::new(1),
+        assets_root: loaded.config.assets_dir.clone(),
+        upload_dir: std::env::temp_dir().join(format!("rust-ocr2md-serve-{}", std::process::id())),
+        uploads: AtomicU64::new(0),
+    });
+    std::fs::create_dir_all(&jobs.upload_dir)
+        .with_context(|| format!("Failed to create {}", jobs.upload_dir.display()))?;
+    let converter = Converter::new(loaded.config.clone())
+        .with_explicit_keys(loaded.explicit.clone())
+        .with_password(args.pipeline.password()?)
+        .with_progress(JobProgress(jobs.clone()));
+
+    // Models are loaded once, before the first request is accepted
+    eprintln!("⏳ Loading OCR engine ({:?})...", loaded.config.engine);
+    converter.load_engine()?;
+
+    let server = Server::http(&args.bind)
+        .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", args.bind, e))?;
+    eprintln!("🌐 Listening on http://{}", args.bind);
+
+    let (queue, submissions) = mpsc::sync_channel(args.queue_size);
+    let worker_jobs = jobs.clone();
+    std::thread::spawn(move || work(&converter, &worker_jobs, submissions));
+
+    for request in server.incoming_requests() {
+        // Uploads are read on their own thread so a slow client does not hold up polling
+        if *request.method() == Method::Post {
+            let (jobs, queue) = (jobs.clone(), queue.clone());
+            std::thread::spawn(move || respond(request, &jobs, &queue));
+        } else {
+            respond(request, &jobs, &queue);
+        }
+    }
+    Ok(())
+}
+
+fn respond(request: Request, jobs: &Jobs, queue: &SyncSender<Submission>) {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    if let Err(e) = handle(request, jobs, queue) {
+        eprintln!("⚠️  {} {}: {}", method, url, e);
+    }
+}
+
+/// Convert submissions one at a time with the warm converter.
+fn work(converter: &Converter, jobs: &Jobs, submissions: Receiver<Submission>) {
+    for submission in submissions {
+        let id = submission.id;
+        let mut name = String::new();
+        jobs.update(id, |job| {
+            job.status = JobStatus::Running;
+            job.started = Some(Instant::now());
+            name = job.name.clone();
+        });
+        eprintln!("▶️  Job {}: {}", id, name);
+
+        // Ids start again at 1 after a restart; do not keep another server's figures
+        jobs.remove_assets(id);
+        let start = Instant::now();
+        let bytes = std::fs::read(&submission.upload)
+            .with_context(|| format!("Failed to read upload {}", submission.upload.display()));
+        let _ = std::fs::remove_file(&submission.upload);
+        // A panic fails this job instead of stopping the only worker
+        let result = bytes.and_then(|bytes| {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                converter.convert_bytes_with(bytes, Path::new(&name), &submission.options)
+            }))
+            .unwrap_or_else(|panic| {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                Err(anyhow::anyhow!("converter panicked: {}", message))
+            })
+        });
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+
+        match &result {
+            Ok(document) => eprintln!(
+                "✅ Job {}: {}/{} pages in {:.2}s",
+                id,
+                document.pages.len(),
+                document.selected,
+                elapsed_ms as f64 / 1000.0
+            ),
+            Err(e) => eprintln!("❌ Job {}: {:#}", id, e),
+        }
+        let mut table = jobs.table.lock().unwrap();
+        if let Some(job) = table.get_mut(&id) {
+            job.elapsed_ms = Some(elapsed_ms);
+            match result {
+                Ok(document) => {
+                    job.status = JobStatus::Done;
+                    job.pages = Some(document.selected);
+                    job.document = Some(document);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(format!("{:#}", e));
+                }
+            }
+        }
+        jobs.evict(&mut table);
+    }
+}
+
+fn handle(mut request: Request, jobs: &Jobs, queue: &SyncSender<Submission>) -> Result<()> {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let job_id = |s: &str| s.parse::<u64>().ok();
+
+    let response = match (&method, segments.as_slice()) {
+        (Method::Get, ["health"]) => json_response(200, json!({ "status": "ok" })),
+        (Method::Post, ["jobs"]) => submit(&mut request, query, jobs, queue),
+        (Method::Get, ["jobs"]) => {
+            let ids: Vec<u64> = jobs.table.lock().unwrap().keys().copied().collect();
+            let statuses: Vec<_> = ids.into_iter().filter_map(|id| jobs.status(id)).collect();
+            json_response(200, json!({ "jobs": statuses }))
+        }
+        (Method::Get, ["jobs", id]) => match job_id(id).and_then(|id| jobs.status(id)) {
+            Some(status) => json_response(200, status),
+            None => error_response(404, "no such job"),
+        },
+        (Method::Get, ["jobs", id, kind @ ("markdown" | "result")]) => {
+            let table = jobs.table.lock().unwrap();
+            match job_id(id).and_then(|id| table.get(&id)) {
+                None => error_response(404, "no such job"),
+                Some(Job {
+                    document: Some(document),
+                    ..
+                }) if *kind == "markdown" => Response::from_string(document.markdown.clone())
+                    .with_header(header("Content-Type", "text/markdown; charset=utf-8")),
+                Some(Job {
+                    document: Some(document),
+                    ..
+                }) => json_response(
+                    200,
+                    json!({
+                        "id": id,
+                        "markdown": document.markdown,
+                        "pages": document.pages,
+                        "failures": document.failures,
+                        "report": document.report,
+                    }),
+                ),
+                Some(job) if job.status == JobStatus::Failed => {
+                    error_response(422, job.error.as_deref().unwrap_or("conversion failed"))
+                }
+                Some(_) => error_response(409, "job has not finished"),
+            }
+        }
+        (Method::Delete, ["jobs", id]) => {
+            let mut table = jobs.table.lock().unwrap();
+            match job_id(id).map(|id| (id, table.get(&id).map(|job| job.status))) {
+                Some((id, Some(JobStatus::Done | JobStatus::Failed))) => {
+                    table.remove(&id);
+                    jobs.remove_assets(id);
+                    json_response(200, json!({ "deleted": id.to_string() }))
+                }
+                Some((_, Some(_))) => error_response(409, "job is queued or running"),
+                _ => error_response(404, "no such job"),
+            }
+        }
+        _ => error_response(404, "not found"),
+    };
+
+    eprintln!("{} {} → {}", method, path, response.status_code().0);
+    request.respond(response)?;
+    Ok(())
+}
+
+/// `POST /jobs?pages=1-10&name=paper.pdf` with the PDF as the request body.
+fn submit(
+    request: &mut Request,
+    query: &str,
+    jobs: &Jobs,
+    queue: &SyncSender<Submission>,
+) -> Response<Cursor<Vec<u8>>> {
+    if request
+        .body_length()
+        .is_some_and(|len| len as u64 > MAX_UPLOAD_BYTES)
+    {
+        return error_response(413, "upload too large");
+    }
+    // Written to disk, so queued jobs do not hold their PDFs in memory
+    let upload = jobs.upload_dir.join(format!(
+        "upload-{}.pdf",
+        jobs.uploads.fetch_add(1, Ordering::SeqCst)
+    ));
+    let written = File::create(&upload).and_then(|mut file| {
+        std::io::copy(
+            &mut request.as_reader().take(MAX_UPLOAD_BYTES + 1),
+            &mut file,
+        )
+    });
+    let rejected = match written {
+        Err(e) => Some(error_response(400, &format!("failed to read body: {}", e))),
+        Ok(len) if len > MAX_UPLOAD_BYTES => Some(error_response(413, "upload too large")),
+        Ok(0) => Some(error_response(400, "send the PDF as the request body")),
+        Ok(_) => None,
+    };
+    if let Some(response) = rejected {
+        let _ = std::fs::remove_file(&upload);
+        return response;
+    }
+
+    // Registered first so the worker always finds the job it is handed; the id is taken
+    // under the lock so ids follow queue order
+    let params = parse_query(query);
+    let mut table = jobs.table.lock().unwrap();
+    let id = jobs.next_id.fetch_add(1, Ordering::SeqCst);
+    let name = params
+        .get("name")
+        .cloned()
+        .unwrap_or_else(|| format!("job-{}.pdf", id));
+    let submission = Submission {
+        id,
+        upload: upload.clone(),
+        options: ConvertOptions {
+            pages: params.get("pages").cloned(),
+            assets_dir: Some(jobs.assets_root.join(id.to_string())),
+            ..Default::default()
+        },
+    };
+    table.insert(
+        id,
+        Job {
+            name,
+            status: JobStatus::Queued,
+            pages: None,
+            converted: 0,
+            failed: 0,
+            started: None,
+            elapsed_ms: None,
+            error: None,
+            document: None,
+        },
+    );
+    let response = match queue.try_send(submission) {
+        Ok(()) => {
+            drop(table);
+            return json_response(202, jobs.status(id).unwrap_or_default());
+        }
+        Err(TrySendError::Full(_)) => error_response(503, "job queue is full, retry later"),
+        Err(TrySendError::Disconnected(_)) => error_response(500, "converter is not running"),
+    };
+    table.remove(&id);
+    let _ = std::fs::remove_file(&upload);
+    response
+}
+
+fn parse_query(query: &str) -> BTreeMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (percent_decode(key), percent_decode(value)))
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let byte = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match byte {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn json_response(status: u16, value: serde_json::Value) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(value.to_string())
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn error_response(status: u16, message: &str) -> Response<Cursor<Vec<u8>>> {
+    json_response(status, json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("a%20b+c"), "a b c");
+        assert_eq!(percent_decode("%E4%B8%AD.pdf"), "中.pdf");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn query_parameters() {
+        let params = parse_query("pages=1-10&name=my%20paper.pdf&flag");
+        assert_eq!(params.len(), 2);
+        assert_eq!(params["pages"], "1-10");
+        assert_eq!(params["name"], "my paper.pdf");
+        assert!(parse_query("").is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rust_ocr2md::manifest::{self, Manifest};
+use rust_ocr2md::{ConvertOptions, Converter};
+
+use crate::{batch, interrupt, PipelineArgs};
+
+/// Default state file name, kept in the output directory.
+const STATE_FILE: &str = ".rust-ocr2md-watch.json";
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Folder to watch; files directly inside it are converted
+    dir: PathBuf,
+
+    /// Output directory for the Markdown files
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Seconds between scans of the folder
+    #[arg(long, default_value = "5")]
+    interval: u64,
+
+    /// Seconds a file's size and modification time must stay unchanged before it is converted
+    #[arg(long, default_value = "10")]
+    settle: u64,
+
+    /// Where converted originals are moved (default: <DIR>/done)
+    #[arg(long)]
+    done_dir: Option<PathBuf>,
+
+    /// Where originals that failed or lost pages are moved (default: <DIR>/failed)
+    #[arg(long)]
+    failed_dir: Option<PathBuf>,
+
+    /// State file recording processed files (default: <OUT>/.rust-ocr2md-watch.json)
+    #[arg(long)]
+    state: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub(crate) pipeline: PipelineArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum FileStatus {
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FileState {
+    /// Size and modification time of the file that was converted
+    fingerprint: String,
+    status: FileStatus,
+    output: PathBuf,
+    finished: String,
+    error: Option<String>,
+}
+
+/// Files already handled, by name, so a restart does not convert them again.
+#[derive(Default, Serialize, Deserialize)]
+struct WatchState {
+    files: BTreeMap<String, FileState>,
+}
+
+impl WatchState {
+    fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid watch state file {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e)
+                .with_context(|| format!("Failed to read watch state file {}", path.display())),
+        }
+    }
+
+    /// Written to a temporary file first so a crash never leaves it half written.
+    fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .with_context(|| format!("Failed to write watch state file {}", path.display()))
+    }
+
+    fn is_processed(&self, name: &str, fingerprint: &str) -> bool {
+        self.files
+            .get(name)
+            .is_some_and(|state| state.fingerprint == fingerprint)
+    }
+}
+
+pub fn run(args: &WatchArgs) -> Result<()> {
+    eprintln!("🚀 RustOCR2md - High-performance PDF OCR Tool");
+    eprintln!("================================================");
+
+    if !args.dir.is_dir() {
+        anyhow::bail!("Watch folder does not exist: {}", args.dir.display());
+    }
+    let done_dir = args
+        .done_dir
+        .clone()
+        .unwrap_or_else(|| args.dir.join("done"));
+    let failed_dir = args
+        .failed_dir
+        .clone()
+        .unwrap_or_else(|| args.dir.join("failed"));
+    for dir in [&args.out, &done_dir, &failed_dir] {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let state_path = args
+        .state
+        .clone()
+        .unwrap_or_else(|| args.out.join(STATE_FILE));
+    let mut state = WatchState::load(&state_path)?;
+
+    let loaded = args.pipeline.load_config()?;
+    crate::print_config_sources(&loaded);
+    let converter = args.pipeline.converter(&loaded)?;
+    converter.load_engine()?;
+
+    eprintln!("👀 Watching {} (Ctrl-C to stop)", args.dir.display());
+    eprintln!("📂 Output: {}", args.out.display());
+    eprintln!("🗂️  State: {}", state_path.display());
+
+    // Files seen but not yet settled: fingerprint and when it was first seen unchanged
+    let mut pending: HashMap<PathBuf, (String, Instant)> = HashMap::new();
+    let settle = Duration::from_secs(args.settle);
+    while !interrupt::requested() {
+        let files = scan(&args.dir)?;
+        pending.retain(|file, _| files.contains(file));
+
+        let mut ready = Vec::new();
+        for file in files {
+            let name = file_name(&file);
+            let fingerprint = manifest::input_fingerprint(&file);
+            if state.is_processed(&name, &fingerprint) {
+                continue;
+            }
+            match pending.get(&file) {
+                Some((seen, since)) if *seen == fingerprint => {
+                    // A file still being written may also be locked against reading
+                    if since.elapsed() >= settle && File::open(&file).is_ok() {
+                        ready.push(file);
+                    }
+                }
+                _ => {
+                    pending.insert(file, (fingerprint, Instant::now()));
+                }
+            }
+        }
+
+        for file in ready {
+            pending.remove(&file);
+            let name = file_name(&file);
+            let output = output_for(&args.out, &name, &state);
+            if let Some(file_state) =
+                convert(&converter, &file, &output, args, &done_dir, &failed_dir)
+            {
+                state.files.insert(name, file_state);
+                state.save(&state_path)?;
+            }
+            if interrupt::requested() {
+                break;
+            }
+        }
+
+        sleep_unless_interrupted(Duration::from_secs(args.interval));
+    }
+
+    eprintln!("\n👋 Stopped watching");
+    Ok(())
+}
+
+/// Convert one settled file and move it away; `None` if it was interrupted and stays put.
+fn convert(
+    converter: &Converter,
+    file: &Path,
+    output: &Path,
+    args: &WatchArgs,
+    done_dir: &Path,
+    failed_dir: &Path,
+) -> Option<FileState> {
+    let name = file_name(file);
+    let fingerprint = manifest::input_fingerprint(file);
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    eprintln!("\n📄 {}", file.display());
+
+    // A run cut short by Ctrl-C left a manifest; pick up where it stopped
+    let options = ConvertOptions {
+        pages: None,
+        output: Some(output.to_path_buf()),
+        stdout: false,
+        assets_dir: Some(args.out.join(&converter.config().assets_dir).join(stem)),
+        resume: Manifest::path_for(output).exists(),
+        json: None,
+    };
+    let error = match converter.convert(file, &options) {
+        Ok(document) if !document.interrupted.is_empty() => {
+            eprintln!(
+                "⏸️  Interrupted; {} will be resumed on the next start",
+                name
+            );
+            return None;
+        }
+        Ok(document) if document.is_complete() => {
+            eprintln!("✅ {} → {}", name, output.display());
+            None
+        }
+        Ok(document) => {
+            crate::print_failures(&document.failures, args.pipeline.progress);
+            Some(format!(
+                "{} of {} pages failed:\n{}",
+                document.failures.len(),
+                document.selected,
+                document
+                    .failures
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ))
+        }
+        Err(e) => {
+            eprintln!("❌ {}: {:#}", name, e);
+            Some(format!("{:#}", e))
+        }
+    };
+
+    let (status, target_dir) = match error {
+        None => (FileStatus::Done, done_dir),
+        Some(_) => (FileStatus::Failed, failed_dir),
+    };
+    match move_into(file, target_dir) {
+        Ok(target) => {
+            if let Some(error) = &error {
+                let error_file = target.with_extension("error.txt");
+                if let Err(e) = std::fs::write(&error_file, error) {
+                    eprintln!("⚠️  Failed to write {}: {}", error_file.display(), e);
+                }
+            }
+        }
+        // The state file still keeps it from being converted again
+        Err(e) => eprintln!("⚠️  {:#}", e),
+    }
+
+    Some(FileState {
+        fingerprint,
+        status,
+        output: output.to_path_buf(),
+        finished: Local::now().to_rfc3339(),
+        error,
+    })
+}
+
+/// `<stem>.md` in `out`, or `<name>.md` when a file of another name already wrote that,
+/// e.g. a.png after a.pdf.
+fn output_for(out: &Path, name: &str, state: &WatchState) -> PathBuf {
+    let output = out.join(name).with_extension("md");
+    let taken = state
+        .files
+        .iter()
+        .any(|(other, file)| other != name && file.output == output);
+    if taken {
+        out.join(format!("{}.md", name))
+    } else {
+        output
+    }
+}
+
+/// Supported files directly inside `dir`, in name order.
+fn scan(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && batch::is_supported(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Move `file` into `dir`, adding a timestamp if a file of that name is already there.
+fn move_into(file: &Path, dir: &Path) -> Result<PathBuf> {
+    let name = file_name(file);
+    let mut target = dir.join(&name);
+    if target.exists() {
+        let stem = target
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stamped = match target.extension() {
+            Some(ext) => format!(
+                "{}-{}.{}",
+                stem,
+                Local::now().format("%Y%m%d-%H%M%S"),
+                ext.to_string_lossy()
+            ),
+            None => format!("{}-{}", stem, Local::now().format("%Y%m%d-%H%M%S")),
+        };
+        target = dir.join(stamped);
+    }
+
+    // A rename cannot cross file systems, e.g. from a network share
+    if std::fs::rename(file, &target).is_err() {
+        std::fs::copy(file, &target)
+            .and_then(|_| std::fs::remove_file(file))
+            .with_context(|| format!("Failed to move {} to {}", file.display(), dir.display()))?;
+    }
+    Ok(target)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn sleep_unless_interrupted(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    while !interrupt::requested() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}